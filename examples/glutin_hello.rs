@@ -23,6 +23,12 @@ use phosphorus::widget::*;
 
 //static HELLO_MARKUP: &'static str = include_str!("assets/hello-markup.jade");
 
+/// The state of the example, which the buttons' callbacks change.
+struct App {
+    clicks: u32,
+    toggle_theme: bool
+}
+
 fn main() {
     // Set up our window
     let (mut stream, mut device, mut factory) = {
@@ -35,8 +41,8 @@ fn main() {
     };
 
     // Set up our Phosphorus UI
+    let mut app = App { clicks: 0, toggle_theme: false };
     let root = phosphorus::widget::LayoutBuilder::new()
-        .with_background_color([21, 23, 24])
         .with_widget(phosphorus::widget::TextBuilder::new()
             .with_text("Hello World!")
             .build_boxed())
@@ -46,36 +52,99 @@ fn main() {
         .with_widget(phosphorus::widget::ImageBuilder::new()
             .with_source("./examples/assets/test.png")
             .with_size([200, 200])
-            .build_boxed(&mut factory))
+            .build_boxed(&mut factory).unwrap())
         .with_widget(phosphorus::widget::TextBuilder::new()
             .with_text("Hello from after the image!")
             .build_boxed())
         .with_widget(phosphorus::widget::ButtonBuilder::new()
             .with_text("Click me?")
-            .with_callback(Box::new(|| println!("Hello")))
+            .with_callback(Box::new(|context| {
+                println!("Hello from {:?} at {:?}", context.id(), context.position());
+
+                // Widgets can't be changed while the click travels through
+                // the tree, so the text is updated once it's done
+                let clicks = {
+                    let app = context.app_state::<App>().unwrap();
+                    app.clicks += 1;
+                    app.clicks
+                };
+                context.queue_change(move |root: &mut Layout<gfx_device_gl::Resources>| {
+                    let text = root.find_by_id_mut("clicks")
+                        .and_then(|w| w.as_any_mut().downcast_mut::<Text<gfx_device_gl::Resources>>());
+                    if let Some(text) = text {
+                        text.set_text(&format!("Clicked {} times", clicks));
+                    }
+                }).unwrap();
+            }))
+            .build_boxed())
+        .with_widget(phosphorus::widget::TextBuilder::new()
+            .with_id("clicks")
+            .with_text("Not clicked yet")
+            .build_boxed())
+        .with_widget(phosphorus::widget::ButtonBuilder::new()
+            .with_text("Switch theme")
+            .with_size([110, 20])
+            .with_callback(Box::new(|context| context.app_state::<App>().unwrap().toggle_theme = true))
             .build_boxed())
         .build();
-    let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root);
+    let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root).unwrap();
+    gui.set_theme(phosphorus::style::Theme::dark());
+    let mut light = false;
 
-    // Run our actual UI loop
+    // Run our actual UI loop, glutin doesn't tell where mouse buttons are
+    // pressed or which modifier keys are held down
+    let mut mouse_pos = [0, 0];
+    let mut modifiers = phosphorus::Modifiers::new();
     'main: loop {
         // Quit when the window is closed
         for event in stream.out.window.poll_events() {
             match event {
                 glutin::Event::Closed => break 'main,
-                glutin::Event::MouseMoved((x, y)) =>
-                    gui.raise_event(&stream, phosphorus::Event::MouseMoved([x, y])),
-                glutin::Event::MouseInput(glutin::ElementState::Released, _) =>
-                    gui.raise_event(&stream, phosphorus::Event::MouseClick),
+                glutin::Event::MouseMoved((x, y)) => {
+                    mouse_pos = [x, y];
+                    gui.raise_event_with(&stream, phosphorus::Event::MouseMoved(mouse_pos), &mut app);
+                },
+                glutin::Event::MouseInput(glutin::ElementState::Pressed, button) => {
+                    gui.raise_event_with(&stream, phosphorus::Event::MouseDown(mouse_button(button), mouse_pos), &mut app);
+                },
+                glutin::Event::MouseInput(glutin::ElementState::Released, button) => {
+                    gui.raise_event_with(&stream, phosphorus::Event::MouseUp(mouse_button(button), mouse_pos), &mut app);
+                },
+                glutin::Event::MouseWheel(delta) => {
+                    gui.raise_event_with(&stream, phosphorus::Event::MouseWheel([0.0, delta as f32]), &mut app);
+                },
+                glutin::Event::KeyboardInput(state, _, Some(code)) => {
+                    let key = key(code);
+                    let pressed = state == glutin::ElementState::Pressed;
+                    match key {
+                        phosphorus::Key::Shift => modifiers.shift = pressed,
+                        phosphorus::Key::Control => modifiers.control = pressed,
+                        phosphorus::Key::Alt => modifiers.alt = pressed,
+                        phosphorus::Key::Logo => modifiers.logo = pressed,
+                        _ => ()
+                    }
+                    let event = if pressed { phosphorus::Event::KeyDown(key, modifiers) } else { phosphorus::Event::KeyUp(key, modifiers) };
+                    gui.raise_event_with(&stream, event, &mut app);
+                },
+                glutin::Event::ReceivedCharacter(c) => {
+                    gui.raise_event_with(&stream, phosphorus::Event::TextInput(c), &mut app);
+                },
                 _ => (),
             }
         }
 
+        // Switch between the dark and light theme when requested
+        if app.toggle_theme {
+            app.toggle_theme = false;
+            light = !light;
+            gui.set_theme(if light { phosphorus::style::Theme::light() } else { phosphorus::style::Theme::dark() });
+        }
+
         stream.clear(gfx::ClearData {color: [1.0, 1.0, 1.0, 1.0], depth: 1.0, stencil: 0});
 
         {
             // Render our actual GUI
-            gui.render(&mut factory, &mut stream);
+            gui.render(&mut factory, &mut stream).unwrap();
         }
 
         // Show the rendered to buffer on the screen
@@ -85,3 +154,39 @@ fn main() {
         device.cleanup();
     }
 }
+
+fn mouse_button(button: glutin::MouseButton) -> phosphorus::MouseButton {
+    match button {
+        glutin::MouseButton::Left => phosphorus::MouseButton::Left,
+        glutin::MouseButton::Right => phosphorus::MouseButton::Right,
+        glutin::MouseButton::Middle => phosphorus::MouseButton::Middle,
+        glutin::MouseButton::Other(n) => phosphorus::MouseButton::Other(n)
+    }
+}
+
+fn key(code: glutin::VirtualKeyCode) -> phosphorus::Key {
+    use glutin::VirtualKeyCode as V;
+    use phosphorus::Key as K;
+    match code {
+        V::Escape => K::Escape,
+        V::Tab => K::Tab,
+        V::Back => K::Backspace,
+        V::Return => K::Enter,
+        V::Space => K::Space,
+        V::Insert => K::Insert,
+        V::Delete => K::Delete,
+        V::Home => K::Home,
+        V::End => K::End,
+        V::PageUp => K::PageUp,
+        V::PageDown => K::PageDown,
+        V::Left => K::Left,
+        V::Right => K::Right,
+        V::Up => K::Up,
+        V::Down => K::Down,
+        V::LShift | V::RShift => K::Shift,
+        V::LControl | V::RControl => K::Control,
+        V::LAlt | V::RAlt | V::LMenu | V::RMenu => K::Alt,
+        V::LWin | V::RWin => K::Logo,
+        code => K::Other(code as u32)
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate phosphorus;
+extern crate glutin;
+extern crate gfx;
+extern crate gfx_device_gl;
+extern crate gfx_window_glutin;
+
+use gfx::traits::*;
+
+fn main() {
+    // Set up our window
+    let (mut stream, mut device, mut factory) = {
+        let window = glutin::WindowBuilder::new()
+            .with_vsync()
+            .with_dimensions(600, 500)
+            .with_title(String::from("Phosphorus Markup"))
+            .build_strict().unwrap();
+        gfx_window_glutin::init(window)
+    };
+
+    // Set up our Phosphorus UI from a markup file, changes to it are picked up while running
+    let mut watcher = phosphorus::markup::ScreenWatcher::new("./examples/assets/hello-markup.jade")
+        .with_callback("click-me-1", |_| println!("Hello"))
+        .with_callback("click-me-2", |context| println!("Hello again, shift held: {}", context.modifiers().shift));
+    let root = watcher.load(&mut factory).unwrap();
+    let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root).unwrap();
+
+    // Run our actual UI loop, glutin doesn't tell where mouse buttons are
+    // pressed or which modifier keys are held down
+    let mut mouse_pos = [0, 0];
+    let mut modifiers = phosphorus::Modifiers::new();
+    'main: loop {
+        // Reload the markup file if it changed
+        if let Err(e) = watcher.poll(&mut gui, &mut factory) {
+            println!("{}", e);
+        }
+
+        // Quit when the window is closed
+        for event in stream.out.window.poll_events() {
+            match event {
+                glutin::Event::Closed => break 'main,
+                glutin::Event::MouseMoved((x, y)) => {
+                    mouse_pos = [x, y];
+                    gui.raise_event(&stream, phosphorus::Event::MouseMoved(mouse_pos));
+                },
+                glutin::Event::MouseInput(glutin::ElementState::Pressed, button) => {
+                    gui.raise_event(&stream, phosphorus::Event::MouseDown(mouse_button(button), mouse_pos));
+                },
+                glutin::Event::MouseInput(glutin::ElementState::Released, button) => {
+                    gui.raise_event(&stream, phosphorus::Event::MouseUp(mouse_button(button), mouse_pos));
+                },
+                glutin::Event::MouseWheel(delta) => {
+                    gui.raise_event(&stream, phosphorus::Event::MouseWheel([0.0, delta as f32]));
+                },
+                glutin::Event::KeyboardInput(state, _, Some(code)) => {
+                    let key = key(code);
+                    let pressed = state == glutin::ElementState::Pressed;
+                    match key {
+                        phosphorus::Key::Shift => modifiers.shift = pressed,
+                        phosphorus::Key::Control => modifiers.control = pressed,
+                        phosphorus::Key::Alt => modifiers.alt = pressed,
+                        phosphorus::Key::Logo => modifiers.logo = pressed,
+                        _ => ()
+                    }
+                    let event = if pressed { phosphorus::Event::KeyDown(key, modifiers) } else { phosphorus::Event::KeyUp(key, modifiers) };
+                    gui.raise_event(&stream, event);
+                },
+                glutin::Event::ReceivedCharacter(c) => {
+                    gui.raise_event(&stream, phosphorus::Event::TextInput(c));
+                },
+                _ => (),
+            }
+        }
+
+        stream.clear(gfx::ClearData {color: [1.0, 1.0, 1.0, 1.0], depth: 1.0, stencil: 0});
+
+        {
+            // Render our actual GUI
+            gui.render(&mut factory, &mut stream).unwrap();
+        }
+
+        // Show the rendered to buffer on the screen
+        //stream.present(&mut device); ICE!
+        stream.flush(&mut device);
+        stream.out.window.swap_buffers();
+        device.cleanup();
+    }
+}
+
+fn mouse_button(button: glutin::MouseButton) -> phosphorus::MouseButton {
+    match button {
+        glutin::MouseButton::Left => phosphorus::MouseButton::Left,
+        glutin::MouseButton::Right => phosphorus::MouseButton::Right,
+        glutin::MouseButton::Middle => phosphorus::MouseButton::Middle,
+        glutin::MouseButton::Other(n) => phosphorus::MouseButton::Other(n)
+    }
+}
+
+fn key(code: glutin::VirtualKeyCode) -> phosphorus::Key {
+    use glutin::VirtualKeyCode as V;
+    use phosphorus::Key as K;
+    match code {
+        V::Escape => K::Escape,
+        V::Tab => K::Tab,
+        V::Back => K::Backspace,
+        V::Return => K::Enter,
+        V::Space => K::Space,
+        V::Insert => K::Insert,
+        V::Delete => K::Delete,
+        V::Home => K::Home,
+        V::End => K::End,
+        V::PageUp => K::PageUp,
+        V::PageDown => K::PageDown,
+        V::Left => K::Left,
+        V::Right => K::Right,
+        V::Up => K::Up,
+        V::Down => K::Down,
+        V::LShift | V::RShift => K::Shift,
+        V::LControl | V::RControl => K::Control,
+        V::LAlt | V::RAlt | V::LMenu | V::RMenu => K::Alt,
+        V::LWin | V::RWin => K::Logo,
+        code => K::Other(code as u32)
+    }
+}
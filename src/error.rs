@@ -0,0 +1,84 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use markup;
+
+/// Represents an error that occurred while setting up or rendering a Gui.
+#[derive(Debug)]
+pub enum Error {
+    /// A shader program could not be linked by the device.
+    Shader(String),
+    /// The font used for text rendering could not be loaded.
+    Font(String),
+    /// An image could not be loaded into a texture.
+    Texture(String),
+    /// A draw call was rejected by the device.
+    Draw(String),
+    /// A markup document could not be parsed.
+    Markup(markup::ParseError),
+    /// A file could not be read.
+    Io(std::io::Error),
+    /// A change was queued for a widget tree of other resources than the
+    /// event is dispatched through.
+    TreeMismatch
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            &Error::Shader(ref e) => write!(f, "failed to link shader program: {}", e),
+            &Error::Font(ref e) => write!(f, "failed to load font: {}", e),
+            &Error::Texture(ref e) => write!(f, "failed to load texture: {}", e),
+            &Error::Draw(ref e) => write!(f, "failed to draw: {}", e),
+            &Error::Markup(ref e) => write!(f, "failed to parse markup: {}", e),
+            &Error::Io(ref e) => write!(f, "failed to read file: {}", e),
+            &Error::TreeMismatch => write!(f, "change queued for a tree of other resources")
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        match self {
+            &Error::Shader(_) => "failed to link shader program",
+            &Error::Font(_) => "failed to load font",
+            &Error::Texture(_) => "failed to load texture",
+            &Error::Draw(_) => "failed to draw",
+            &Error::Markup(_) => "failed to parse markup",
+            &Error::Io(_) => "failed to read file",
+            &Error::TreeMismatch => "change queued for a tree of other resources"
+        }
+    }
+
+    fn cause(&self) -> Option<&std::error::Error> {
+        match self {
+            &Error::Markup(ref e) => Some(e),
+            &Error::Io(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<markup::ParseError> for Error {
+    fn from(e: markup::ParseError) -> Error {
+        Error::Markup(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use gfx;
+use widget::{self, Widget};
+
+/// Tracks which widget in a tree receives keyboard input.
+///
+/// The focused widget is remembered by the path of child indices leading to
+/// it, and also has its `focused` state set. If the tree changes so the path
+/// no longer leads to a focused, focusable widget, nothing is focused.
+pub struct FocusManager {
+    focused: Option<Vec<usize>>
+}
+
+impl FocusManager {
+    /// Initializes a new `FocusManager` with nothing focused.
+    pub fn new() -> FocusManager {
+        FocusManager {
+            focused: None
+        }
+    }
+
+    /// Gets the path to the focused widget in a tree, if it's still there.
+    pub fn focused_path<R: gfx::Resources>(&self, root: &Widget<R>) -> Option<&[usize]> {
+        match self.focused {
+            Some(ref path) => match widget::descendant(root, path) {
+                Some(w) if w.is_focusable() && w.state().focused => Some(path),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    /// Gets the focused widget in a tree as mutable, if it's still there.
+    pub fn focused<'a, R: gfx::Resources>(&mut self, root: &'a mut Widget<R>) -> Option<&'a mut Widget<R>> {
+        // Forget a widget that can't be focused anymore, such as after it was disabled
+        let path = self.focused_path(root).map(|p| p.to_vec());
+        match path {
+            Some(path) => widget::descendant_mut(root, &path),
+            None => {
+                self.set_focus(root, None);
+                None
+            }
+        }
+    }
+
+    /// Focuses the widget at a path in a tree, or nothing. Returns if the
+    /// widget was focused, which it isn't if it can't be.
+    pub fn set_focus<R: gfx::Resources>(&mut self, root: &mut Widget<R>, path: Option<Vec<usize>>) -> bool {
+        if let Some(old) = self.focused.take() {
+            if let Some(w) = widget::descendant_mut(root, &old) {
+                w.state_mut().focused = false;
+            }
+        }
+
+        if let Some(path) = path {
+            if let Some(w) = widget::descendant_mut(root, &path) {
+                if w.is_focusable() {
+                    w.state_mut().focused = true;
+                    self.focused = Some(path);
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Finds the focused widget in a tree again by its `focused` state, after
+    /// the tree changed so the widget may be at another path.
+    pub fn find_focused<R: gfx::Resources>(&mut self, root: &mut Widget<R>) {
+        let paths = widget::find_paths(root, &|w: &Widget<R>| w.state().focused);
+        self.focused = None;
+
+        // Only the first focusable widget keeps its focus
+        for path in paths {
+            if let Some(w) = widget::descendant_mut(root, &path) {
+                if self.focused.is_none() && w.is_focusable() {
+                    self.focused = Some(path.clone());
+                } else {
+                    w.state_mut().focused = false;
+                }
+            }
+        }
+    }
+
+    /// Focuses the widget at a path in a tree, or its closest ancestor that
+    /// can be focused, such as after a click. Focuses nothing if none can be.
+    pub fn focus_at<R: gfx::Resources>(&mut self, root: &mut Widget<R>, path: &[usize]) {
+        let mut target = None;
+        for length in (0..path.len() + 1).rev() {
+            if widget::descendant(root, &path[..length]).map(|w| w.is_focusable()).unwrap_or(false) {
+                target = Some(path[..length].to_vec());
+                break;
+            }
+        }
+
+        self.set_focus(root, target);
+    }
+
+    /// Moves focus to the next focusable widget in tree order, or the previous
+    /// one if `backwards`, wrapping around at the ends.
+    pub fn focus_next<R: gfx::Resources>(&mut self, root: &mut Widget<R>, backwards: bool) {
+        let paths = widget::find_paths(root, &|w: &Widget<R>| w.is_focusable());
+        if paths.is_empty() {
+            self.set_focus(root, None);
+            return;
+        }
+
+        let current = self.focused_path(root).and_then(|f| paths.iter().position(|p| &p[..] == f));
+        let next = match (current, backwards) {
+            (Some(i), false) => (i + 1) % paths.len(),
+            (Some(i), true) => (i + paths.len() - 1) % paths.len(),
+            (None, false) => 0,
+            (None, true) => paths.len() - 1
+        };
+
+        self.set_focus(root, Some(paths[next].clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use widget::{ButtonBuilder, Layout, LayoutBuilder, TextBuilder, Widget};
+    use super::*;
+
+    #[test]
+    fn find_focused_follows_the_widget_to_its_new_path() {
+        let mut root: Layout<Resources> = LayoutBuilder::new()
+            .with_widget(ButtonBuilder::new().with_id("a").build_boxed())
+            .with_widget(ButtonBuilder::new().with_id("b").build_boxed())
+            .build();
+        let mut focus = FocusManager::new();
+        assert!(focus.set_focus(&mut root, Some(vec![1])));
+
+        root.insert(0, TextBuilder::new().build_boxed());
+        assert_eq!(focus.focused_path(&root), None);
+
+        focus.find_focused(&mut root);
+        assert_eq!(focus.focused_path(&root), Some(&[2][..]));
+        assert_eq!(focus.focused(&mut root).and_then(|w| w.id().map(String::from)), Some(String::from("b")));
+    }
+
+    #[test]
+    fn find_focused_forgets_removed_widgets() {
+        let mut root: Layout<Resources> = LayoutBuilder::new()
+            .with_widget(ButtonBuilder::new().build_boxed())
+            .build();
+        let mut focus = FocusManager::new();
+        focus.set_focus(&mut root, Some(vec![0]));
+
+        root.remove(0);
+        root.push(ButtonBuilder::new().build_boxed());
+        focus.find_focused(&mut root);
+        assert_eq!(focus.focused_path(&root), None);
+        assert!(!root.child(0).unwrap().state().focused);
+    }
+}
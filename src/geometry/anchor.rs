@@ -0,0 +1,166 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use render::RenderArea;
+
+/// Where an item is pinned on one axis of an area, with offsets in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    /// Pinned at a distance from the left or top edge.
+    Start(i32),
+    /// Pinned at a distance from the right or bottom edge.
+    End(i32),
+    /// Centered, then moved by an offset.
+    Center(i32),
+    /// Stretched between distances from both edges, ignoring the item's size.
+    Stretch(i32, i32)
+}
+
+impl Anchor {
+    /// Gets the start and size of an item on this axis of an area.
+    fn place(&self, start: i32, space: i32, size: i32) -> (i32, i32) {
+        match *self {
+            Anchor::Start(offset) => (start + offset, size),
+            Anchor::End(offset) => (start + space - offset - size, size),
+            Anchor::Center(offset) => (start + (space - size)/2 + offset, size),
+            Anchor::Stretch(from_start, from_end) => (start + from_start, std::cmp::max(space - from_start - from_end, 0))
+        }
+    }
+
+    /// Gets the space needed on this axis to fit an item with its offsets.
+    fn extent(&self, size: i32) -> i32 {
+        match *self {
+            Anchor::Start(offset) | Anchor::End(offset) => offset + size,
+            Anchor::Center(offset) => size + 2*offset.abs(),
+            Anchor::Stretch(from_start, from_end) => from_start + size + from_end
+        }
+    }
+}
+
+/// The anchors pinning an item horizontally and vertically in an area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Anchors {
+    pub horizontal: Anchor,
+    pub vertical: Anchor
+}
+
+impl Anchors {
+    /// Initializes new `Anchors` from the anchors on both axes.
+    pub fn new(horizontal: Anchor, vertical: Anchor) -> Anchors {
+        Anchors {
+            horizontal: horizontal,
+            vertical: vertical
+        }
+    }
+
+    /// Pins an item at distances from the left and top edges.
+    pub fn top_left(left: i32, top: i32) -> Anchors {
+        Anchors::new(Anchor::Start(left), Anchor::Start(top))
+    }
+
+    /// Pins an item at distances from the right and top edges.
+    pub fn top_right(right: i32, top: i32) -> Anchors {
+        Anchors::new(Anchor::End(right), Anchor::Start(top))
+    }
+
+    /// Pins an item at distances from the left and bottom edges.
+    pub fn bottom_left(left: i32, bottom: i32) -> Anchors {
+        Anchors::new(Anchor::Start(left), Anchor::End(bottom))
+    }
+
+    /// Pins an item at distances from the right and bottom edges.
+    pub fn bottom_right(right: i32, bottom: i32) -> Anchors {
+        Anchors::new(Anchor::End(right), Anchor::End(bottom))
+    }
+
+    /// Pins an item at the center of the area.
+    pub fn center() -> Anchors {
+        Anchors::new(Anchor::Center(0), Anchor::Center(0))
+    }
+
+    /// Stretches an item over the whole area, leaving `inset` free at every edge.
+    pub fn fill(inset: i32) -> Anchors {
+        Anchors::new(Anchor::Stretch(inset, inset), Anchor::Stretch(inset, inset))
+    }
+
+    /// Gets the area of an item of a size pinned in an area.
+    pub fn arrange(&self, area: &RenderArea, size: [i32; 2]) -> RenderArea {
+        let x = self.horizontal.place(area.position[0], area.size[0], size[0]);
+        let y = self.vertical.place(area.position[1], area.size[1], size[1]);
+        RenderArea { position: [x.0, y.0], size: [x.1, y.1] }
+    }
+
+    /// Gets the size an area needs to fit an item of a size with its offsets.
+    pub fn extent(&self, size: [i32; 2]) -> [i32; 2] {
+        [self.horizontal.extent(size[0]), self.vertical.extent(size[1])]
+    }
+}
+
+impl Default for Anchors {
+    fn default() -> Anchors { Anchors::top_left(0, 0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use render::RenderArea;
+    use super::*;
+
+    fn area(position: [i32; 2], size: [i32; 2]) -> RenderArea {
+        RenderArea { position: position, size: size }
+    }
+
+    #[test]
+    fn place_pins_items_on_an_axis() {
+        assert_eq!(Anchor::Start(5).place(10, 100, 20), (15, 20));
+        assert_eq!(Anchor::End(5).place(10, 100, 20), (85, 20));
+        assert_eq!(Anchor::Center(0).place(10, 100, 20), (50, 20));
+        assert_eq!(Anchor::Center(-15).place(10, 100, 20), (35, 20));
+        assert_eq!(Anchor::Stretch(5, 15).place(10, 100, 20), (15, 80));
+    }
+
+    #[test]
+    fn place_clamps_stretched_items_to_nothing() {
+        assert_eq!(Anchor::Stretch(60, 60).place(10, 100, 20), (70, 0));
+    }
+
+    #[test]
+    fn extent_fits_the_item_and_its_offsets() {
+        assert_eq!(Anchor::Start(5).extent(20), 25);
+        assert_eq!(Anchor::End(5).extent(20), 25);
+        assert_eq!(Anchor::Center(-15).extent(20), 50);
+        assert_eq!(Anchor::Stretch(5, 15).extent(20), 40);
+        assert_eq!(Anchors::bottom_right(4, 8).extent([20, 10]), [24, 18]);
+    }
+
+    #[test]
+    fn arrange_pins_items_at_each_corner() {
+        let space = area([10, 20], [200, 100]);
+        assert_eq!(Anchors::top_left(4, 8).arrange(&space, [30, 10]), area([14, 28], [30, 10]));
+        assert_eq!(Anchors::top_right(4, 8).arrange(&space, [30, 10]), area([176, 28], [30, 10]));
+        assert_eq!(Anchors::bottom_left(4, 8).arrange(&space, [30, 10]), area([14, 102], [30, 10]));
+        assert_eq!(Anchors::bottom_right(4, 8).arrange(&space, [30, 10]), area([176, 102], [30, 10]));
+    }
+
+    #[test]
+    fn arrange_centers_and_stretches_items() {
+        let space = area([10, 20], [200, 100]);
+        assert_eq!(Anchors::center().arrange(&space, [30, 10]), area([95, 65], [30, 10]));
+        let moved = Anchors::new(Anchor::Center(10), Anchor::Center(-5));
+        assert_eq!(moved.arrange(&space, [30, 10]), area([105, 60], [30, 10]));
+
+        assert_eq!(Anchors::fill(10).arrange(&space, [30, 10]), area([20, 30], [180, 80]));
+        assert_eq!(Anchors::fill(60).arrange(&space, [30, 10]), area([70, 80], [80, 0]));
+    }
+}
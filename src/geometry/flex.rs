@@ -0,0 +1,373 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use geometry::{self, Align};
+use render::RenderArea;
+use widget::Direction;
+
+/// How items are distributed along the main axis of a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    /// The first and last item are at the edges, with equal space between all items.
+    SpaceBetween,
+    /// Each item has equal space on both of its sides.
+    SpaceAround
+}
+
+impl Justify {
+    /// Parses a justification from its name as used in markup and stylesheets, such as `space-between`.
+    pub fn from_name(name: &str) -> Option<Justify> {
+        match name {
+            "start" => Some(Justify::Start),
+            "center" => Some(Justify::Center),
+            "end" => Some(Justify::End),
+            "space-between" => Some(Justify::SpaceBetween),
+            "space-around" => Some(Justify::SpaceAround),
+            _ => None
+        }
+    }
+
+    /// Gets the name of the justification as used in markup and stylesheets.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Justify::Start => "start",
+            Justify::Center => "center",
+            Justify::End => "end",
+            Justify::SpaceBetween => "space-between",
+            Justify::SpaceAround => "space-around"
+        }
+    }
+}
+
+/// Options for placing items in flexible lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flex {
+    /// How items are distributed along the main axis.
+    pub justify: Justify,
+    /// How items are aligned on the cross axis within their line, unless an
+    /// item overrides it.
+    pub align: Align,
+    /// If items that don't fit are moved to a new line, instead of shrinking.
+    pub wrap: bool,
+    /// Space between lines when wrapping.
+    pub line_spacing: i32
+}
+
+impl Default for Flex {
+    fn default() -> Flex {
+        Flex {
+            justify: Justify::Start,
+            align: Align::Stretch,
+            wrap: false,
+            line_spacing: 0
+        }
+    }
+}
+
+/// An item placed by a `Flex`, sizes include the item's margin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlexItem {
+    /// The size the item has before growing or shrinking.
+    pub size: [i32; 2],
+    pub min_size: [i32; 2],
+    pub max_size: [i32; 2],
+    /// The share of free space the item grows by.
+    pub grow: f32,
+    /// The share of missing space the item shrinks by, relative to its size.
+    pub shrink: f32,
+    /// Overrides the alignment of the `Flex` for this item.
+    pub align: Option<Align>
+}
+
+impl FlexItem {
+    /// Initializes a new `FlexItem` that shrinks but doesn't grow, without size limits.
+    pub fn new(size: [i32; 2]) -> FlexItem {
+        FlexItem {
+            size: size,
+            min_size: [0, 0],
+            max_size: [std::i32::MAX, std::i32::MAX],
+            grow: 0.0,
+            shrink: 1.0,
+            align: None
+        }
+    }
+
+    fn clamp(&self, axis: usize, size: f32) -> f32 {
+        size.max(self.min_size[axis] as f32).min(self.max_size[axis] as f32)
+    }
+}
+
+impl Flex {
+    /// Initializes a new `Flex` with items at the start, stretched and not wrapping.
+    pub fn new() -> Flex {
+        Flex::default()
+    }
+
+    /// Arranges items in an area, one after another in a direction with
+    /// `spacing` between them. Returns the area of each item, in the order
+    /// the items were given in.
+    pub fn arrange(
+        &self, area: &RenderArea, direction: Direction, spacing: i32, items: &[FlexItem]
+    ) -> Vec<RenderArea> {
+        let axis = direction.axis();
+        let cross = 1 - axis;
+        let available = area.size[axis] as f32;
+        let mut areas = vec![RenderArea { position: area.position, size: [0, 0] }; items.len()];
+
+        let lines = self.lines(area, axis, spacing, items);
+        let single_line = lines.len() == 1 && !self.wrap;
+
+        let mut line_start = area.position[cross] as f32;
+        for line in &lines {
+            let line_items: Vec<&FlexItem> = line.iter().map(|&i| &items[i]).collect();
+            let sizes = resolve_sizes(&line_items, axis, available, spacing);
+
+            // Distribute the space left over after growing and shrinking
+            let used = sizes.iter().fold(0.0, |a, b| a + b) + (spacing * (line.len() as i32 - 1)) as f32;
+            let free = (available - used).max(0.0);
+            let count = line.len() as f32;
+            let (mut cursor, extra_spacing) = match self.justify {
+                Justify::Start => (0.0, 0.0),
+                Justify::Center => (free/2.0, 0.0),
+                Justify::End => (free, 0.0),
+                Justify::SpaceBetween if line.len() > 1 => (0.0, free/(count - 1.0)),
+                Justify::SpaceBetween => (0.0, 0.0),
+                Justify::SpaceAround => (free/count/2.0, free/count)
+            };
+
+            // A line that doesn't wrap fills the area, otherwise it fits its largest item
+            let line_size = if single_line {
+                area.size[cross] as f32
+            } else {
+                line_items.iter().map(|i| i.clamp(cross, i.size[cross] as f32)).fold(0.0, f32::max)
+            };
+
+            for (n, &i) in line.iter().enumerate() {
+                let item = &items[i];
+
+                // Main axis, placed from the end of the area in reversed directions
+                let start = cursor.round() as i32;
+                let end = (cursor + sizes[n]).round() as i32;
+                areas[i].size[axis] = end - start;
+                areas[i].position[axis] = if direction.is_reversed() {
+                    area.position[axis] + area.size[axis] - end
+                } else {
+                    area.position[axis] + start
+                };
+                cursor += sizes[n] + spacing as f32 + extra_spacing;
+
+                // Cross axis, aligned within the line
+                let (offset, size) = geometry::align(
+                    item.align.unwrap_or(self.align), line_size,
+                    item.size[cross] as f32, |s| item.clamp(cross, s));
+                areas[i].position[cross] = (line_start + offset).round() as i32;
+                areas[i].size[cross] = size.round() as i32;
+            }
+
+            line_start += line_size + self.line_spacing as f32;
+        }
+
+        areas
+    }
+
+    /// Splits items into lines, as indices into `items`.
+    fn lines(&self, area: &RenderArea, axis: usize, spacing: i32, items: &[FlexItem]) -> Vec<Vec<usize>> {
+        if !self.wrap {
+            return vec![(0..items.len()).collect()];
+        }
+
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut line_size = 0;
+        for (i, item) in items.iter().enumerate() {
+            let size = item.clamp(axis, item.size[axis] as f32) as i32;
+
+            // Every line has at least one item, even if it doesn't fit
+            if !line.is_empty() && line_size + spacing + size > area.size[axis] {
+                lines.push(line);
+                line = Vec::new();
+                line_size = 0;
+            }
+
+            line_size += if line.is_empty() { size } else { spacing + size };
+            line.push(i);
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+}
+
+/// Grows or shrinks the sizes of items in a line to fill the available space,
+/// respecting their minimum and maximum sizes.
+fn resolve_sizes(items: &[&FlexItem], axis: usize, available: f32, spacing: i32) -> Vec<f32> {
+    let mut sizes: Vec<f32> = items.iter().map(|i| i.clamp(axis, i.size[axis] as f32)).collect();
+    let mut frozen = vec![false; items.len()];
+    let spacing = (spacing * (items.len() as i32 - 1)) as f32;
+
+    // Items that hit a limit are frozen at it and the rest is distributed again
+    loop {
+        let free = available - spacing - sizes.iter().fold(0.0, |a, b| a + b);
+        let growing = free > 0.0;
+
+        let factor = |i: usize| if growing { items[i].grow } else { items[i].shrink * items[i].size[axis] as f32 };
+        let flexible: Vec<usize> = (0..items.len()).filter(|&i| !frozen[i]).collect();
+        let total = flexible.iter().fold(0.0, |a, &i| a + factor(i));
+        if free == 0.0 || total <= 0.0 {
+            return sizes;
+        }
+
+        let mut targets = sizes.clone();
+        let mut violated = false;
+        for &i in &flexible {
+            let target = sizes[i] + free * factor(i)/total;
+            let clamped = items[i].clamp(axis, target);
+            if clamped != target {
+                sizes[i] = clamped;
+                frozen[i] = true;
+                violated = true;
+            }
+            targets[i] = clamped;
+        }
+
+        if !violated {
+            return targets;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::Align;
+    use render::RenderArea;
+    use widget::Direction;
+    use super::*;
+    use super::resolve_sizes;
+
+    fn area(position: [i32; 2], size: [i32; 2]) -> RenderArea {
+        RenderArea { position: position, size: size }
+    }
+
+    fn growing(width: i32, grow: f32) -> FlexItem {
+        FlexItem { grow: grow, .. FlexItem::new([width, 20]) }
+    }
+
+    /// Gets the position and size of each item on the main axis of a horizontal flex.
+    fn spans(flex: &Flex, width: i32, items: &[FlexItem]) -> Vec<(i32, i32)> {
+        flex.arrange(&area([0, 0], [width, 40]), Direction::Horizontal, 0, items)
+            .iter().map(|a| (a.position[0], a.size[0])).collect()
+    }
+
+    #[test]
+    fn arrange_grows_items_by_their_share() {
+        let items = [growing(50, 1.0), growing(50, 2.0), growing(50, 0.0)];
+        assert_eq!(spans(&Flex::new(), 300, &items), vec![(0, 100), (100, 150), (250, 50)]);
+    }
+
+    #[test]
+    fn arrange_shrinks_items_relative_to_their_size() {
+        let items = [FlexItem::new([100, 20]), FlexItem::new([50, 20])];
+        assert_eq!(spans(&Flex::new(), 100, &items), vec![(0, 67), (67, 33)]);
+
+        let items = [FlexItem::new([100, 20]), FlexItem { shrink: 0.0, .. FlexItem::new([50, 20]) }];
+        assert_eq!(spans(&Flex::new(), 100, &items), vec![(0, 50), (50, 50)]);
+    }
+
+    #[test]
+    fn arrange_places_spacing_between_items() {
+        let areas = Flex::new().arrange(&area([10, 5], [100, 40]), Direction::Horizontal, 4, &[growing(20, 1.0), growing(20, 1.0)]);
+        assert_eq!(areas, vec![area([10, 5], [48, 40]), area([62, 5], [48, 40])]);
+    }
+
+    #[test]
+    fn resolve_sizes_freezes_items_at_their_limits() {
+        // The first item stops growing at its maximum, the second takes the rest
+        let first = FlexItem { max_size: [80, 100], .. growing(50, 1.0) };
+        let second = growing(50, 1.0);
+        assert_eq!(resolve_sizes(&[&first, &second], 0, 300.0, 0), vec![80.0, 220.0]);
+
+        // The first item stops shrinking at its minimum, the second shrinks further
+        let first = FlexItem { min_size: [90, 0], .. FlexItem::new([100, 20]) };
+        let second = FlexItem::new([100, 20]);
+        assert_eq!(resolve_sizes(&[&first, &second], 0, 100.0, 0), vec![90.0, 10.0]);
+
+        // Sizes start within the limits, even without free space
+        let item = FlexItem { min_size: [30, 0], .. FlexItem::new([10, 20]) };
+        assert_eq!(resolve_sizes(&[&item], 0, 30.0, 0), vec![30.0]);
+    }
+
+    #[test]
+    fn arrange_justifies_free_space() {
+        let items = [FlexItem::new([20, 20]), FlexItem::new([20, 20])];
+        let justified = |justify| spans(&Flex { justify: justify, .. Flex::new() }, 100, &items);
+
+        assert_eq!(justified(Justify::Start), vec![(0, 20), (20, 20)]);
+        assert_eq!(justified(Justify::Center), vec![(30, 20), (50, 20)]);
+        assert_eq!(justified(Justify::End), vec![(60, 20), (80, 20)]);
+        assert_eq!(justified(Justify::SpaceBetween), vec![(0, 20), (80, 20)]);
+        assert_eq!(justified(Justify::SpaceAround), vec![(15, 20), (65, 20)]);
+
+        // A single item stays at the start when spacing it out
+        let flex = Flex { justify: Justify::SpaceBetween, .. Flex::new() };
+        assert_eq!(spans(&flex, 100, &items[..1]), vec![(0, 20)]);
+    }
+
+    #[test]
+    fn arrange_lets_items_override_the_alignment() {
+        let flex = Flex { align: Align::Center, .. Flex::new() };
+        let items = [
+            FlexItem::new([20, 20]),
+            FlexItem { align: Some(Align::End), .. FlexItem::new([20, 20]) },
+            FlexItem { align: Some(Align::Stretch), .. FlexItem::new([20, 20]) },
+            FlexItem { align: Some(Align::Stretch), max_size: [100, 30], .. FlexItem::new([20, 20]) }];
+
+        let areas = flex.arrange(&area([0, 10], [100, 40]), Direction::Horizontal, 0, &items);
+        let cross: Vec<(i32, i32)> = areas.iter().map(|a| (a.position[1], a.size[1])).collect();
+        assert_eq!(cross, vec![(20, 20), (30, 20), (10, 40), (10, 30)]);
+    }
+
+    #[test]
+    fn arrange_wraps_oversized_items_on_their_own_line() {
+        let flex = Flex { wrap: true, line_spacing: 5, .. Flex::new() };
+        let items = [
+            FlexItem::new([60, 10]),
+            FlexItem::new([150, 10]),
+            FlexItem::new([30, 10]),
+            FlexItem::new([30, 10])];
+
+        let areas = flex.arrange(&area([0, 0], [100, 100]), Direction::Horizontal, 0, &items);
+        assert_eq!(areas, vec![
+            area([0, 0], [60, 10]),
+            area([0, 15], [100, 10]),
+            area([0, 30], [30, 10]),
+            area([30, 30], [30, 10])]);
+    }
+
+    #[test]
+    fn arrange_places_reversed_directions_from_the_end() {
+        let items = [FlexItem::new([20, 20]), FlexItem::new([30, 30])];
+
+        let areas = Flex::new().arrange(&area([10, 0], [100, 40]), Direction::HorizontalReverse, 0, &items);
+        assert_eq!(areas, vec![area([90, 0], [20, 40]), area([60, 0], [30, 40])]);
+
+        let areas = Flex::new().arrange(&area([0, 10], [40, 100]), Direction::VerticalReverse, 0, &items);
+        assert_eq!(areas, vec![area([0, 90], [40, 20]), area([0, 60], [40, 30])]);
+    }
+}
@@ -0,0 +1,355 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use geometry::{self, Align};
+use render::RenderArea;
+
+/// The size of a row or column in a grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Track {
+    /// A fixed size in pixels.
+    Pixels(i32),
+    /// A share of the space left over after all other tracks, such as `1fr`.
+    Fraction(f32),
+    /// The size of the largest item in the track.
+    Auto
+}
+
+impl Track {
+    /// Parses a track such as `100`, `100px`, `1fr` or `auto`. Negative
+    /// sizes aren't valid.
+    pub fn parse(value: &str) -> Option<Track> {
+        if value == "auto" {
+            Some(Track::Auto)
+        } else if value.ends_with("fr") {
+            match value[..value.len() - 2].parse::<f32>() {
+                Ok(v) if v >= 0.0 => Some(Track::Fraction(v)),
+                _ => None
+            }
+        } else {
+            let number = if value.ends_with("px") { &value[..value.len() - 2] } else { value };
+            match number.parse::<i32>() {
+                Ok(v) if v >= 0 => Some(Track::Pixels(v)),
+                _ => None
+            }
+        }
+    }
+
+    /// Parses a list of tracks separated by whitespace, such as `auto 1fr 100`.
+    pub fn parse_list(value: &str) -> Option<Vec<Track>> {
+        let mut tracks = Vec::new();
+        for part in value.split_whitespace() {
+            match Track::parse(part) {
+                Some(v) => tracks.push(v),
+                None => return None
+            }
+        }
+
+        Some(tracks)
+    }
+}
+
+impl std::fmt::Display for Track {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            &Track::Pixels(v) => write!(f, "{}", v),
+            &Track::Fraction(v) => write!(f, "{}fr", v),
+            &Track::Auto => write!(f, "auto")
+        }
+    }
+}
+
+/// The cells of a grid an item covers, and how it's aligned within them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridCell {
+    pub row: usize,
+    pub column: usize,
+    /// The amount of rows covered, starting at `row`.
+    pub row_span: usize,
+    /// The amount of columns covered, starting at `column`.
+    pub column_span: usize,
+    /// How the item is aligned horizontally and vertically within its cells.
+    pub align: [Align; 2]
+}
+
+impl GridCell {
+    /// Initializes a new `GridCell` covering a single cell, which the item is stretched to fill.
+    pub fn new(row: usize, column: usize) -> GridCell {
+        GridCell {
+            row: row,
+            column: column,
+            row_span: 1,
+            column_span: 1,
+            align: [Align::Stretch, Align::Stretch]
+        }
+    }
+
+    /// Gets the first track covered and the amount of tracks covered on an axis.
+    fn span(&self, axis: usize) -> (usize, usize) {
+        let (start, span) = if axis == 0 { (self.column, self.column_span) } else { (self.row, self.row_span) };
+        (start, std::cmp::max(span, 1))
+    }
+}
+
+impl Default for GridCell {
+    fn default() -> GridCell { GridCell::new(0, 0) }
+}
+
+/// An item placed in a `Grid`, its size includes the item's margin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridItem {
+    pub size: [i32; 2],
+    pub cell: GridCell
+}
+
+/// Rows and columns that items are placed in.
+///
+/// Items in rows or columns beyond the declared ones are placed in added
+/// `Auto` tracks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Grid {
+    pub columns: Vec<Track>,
+    pub rows: Vec<Track>,
+    /// Space left between columns and between rows.
+    pub spacing: [i32; 2]
+}
+
+impl Grid {
+    /// Initializes a new `Grid` with the given tracks and no spacing.
+    pub fn new(columns: Vec<Track>, rows: Vec<Track>) -> Grid {
+        Grid {
+            columns: columns,
+            rows: rows,
+            spacing: [0, 0]
+        }
+    }
+
+    /// Gets the size needed to fit all items at their size, with fractional
+    /// tracks fitting their items like `Auto` tracks.
+    pub fn measure(&self, items: &[GridItem]) -> [i32; 2] {
+        let mut size = [0, 0];
+        for axis in 0..2 {
+            let sizes = self.track_sizes(axis, None, items);
+            let spacing = self.spacing[axis] * std::cmp::max(sizes.len() as i32 - 1, 0);
+            size[axis] = sizes.iter().fold(spacing, |a, b| a + b);
+        }
+
+        size
+    }
+
+    /// Arranges items in the cells of the grid filling an area. Returns the
+    /// area of each item, in the order the items were given in.
+    pub fn arrange(&self, area: &RenderArea, items: &[GridItem]) -> Vec<RenderArea> {
+        let sizes = [
+            self.track_sizes(0, Some(area.size[0]), items),
+            self.track_sizes(1, Some(area.size[1]), items)];
+
+        // Find where every track starts
+        let mut starts = [Vec::new(), Vec::new()];
+        for axis in 0..2 {
+            let mut position = area.position[axis];
+            for size in &sizes[axis] {
+                starts[axis].push(position);
+                position += size + self.spacing[axis];
+            }
+        }
+
+        items.iter().map(|item| {
+            let mut result = RenderArea { position: [0, 0], size: [0, 0] };
+            for axis in 0..2 {
+                let (first, span) = item.cell.span(axis);
+                let last = first + span - 1;
+                let cell_start = starts[axis][first];
+                let cell_size = starts[axis][last] + sizes[axis][last] - cell_start;
+
+                let (offset, size) = geometry::align(
+                    item.cell.align[axis], cell_size as f32, item.size[axis] as f32, |s| s);
+                result.position[axis] = cell_start + offset.round() as i32;
+                result.size[axis] = size.round() as i32;
+            }
+            result
+        }).collect()
+    }
+
+    /// Gets the declared tracks on an axis, followed by `Auto` tracks for any
+    /// items beyond them.
+    fn tracks(&self, axis: usize, items: &[GridItem]) -> Vec<Track> {
+        let mut tracks = if axis == 0 { self.columns.clone() } else { self.rows.clone() };
+        let needed = items.iter().map(|i| { let (start, span) = i.cell.span(axis); start + span }).max().unwrap_or(0);
+        while tracks.len() < needed {
+            tracks.push(Track::Auto);
+        }
+
+        tracks
+    }
+
+    /// Gets the size of every track on an axis. Fractional tracks share the
+    /// `available` space, or fit their items if there's no limit to it.
+    fn track_sizes(&self, axis: usize, available: Option<i32>, items: &[GridItem]) -> Vec<i32> {
+        let tracks = self.tracks(axis, items);
+        let fits_items = |track: &Track| match *track {
+            Track::Auto => true,
+            Track::Fraction(_) => available.is_none(),
+            Track::Pixels(_) => false
+        };
+
+        let mut sizes: Vec<i32> = tracks.iter().map(|t| match *t {
+            Track::Pixels(v) => v,
+            _ => 0
+        }).collect();
+
+        // Fit items covering a single track first, so items spanning multiple
+        // tracks only add the space those tracks don't already have
+        let mut items: Vec<&GridItem> = items.iter().collect();
+        items.sort_by_key(|i| i.cell.span(axis).1);
+        for item in items {
+            let (start, span) = item.cell.span(axis);
+            let fitting: Vec<usize> = (start..start + span).filter(|&t| fits_items(&tracks[t])).collect();
+            if fitting.is_empty() {
+                continue;
+            }
+
+            let covered = (start..start + span).fold(self.spacing[axis] * (span as i32 - 1), |a, t| a + sizes[t]);
+            let missing = item.size[axis] - covered;
+            if missing > 0 {
+                // Spread the missing space evenly, giving the remainder to the first tracks
+                let count = fitting.len() as i32;
+                for (n, &t) in fitting.iter().enumerate() {
+                    sizes[t] += missing/count + if (n as i32) < missing % count { 1 } else { 0 };
+                }
+            }
+        }
+
+        // Share the space left over between fractional tracks
+        if let Some(available) = available {
+            let spacing = self.spacing[axis] * std::cmp::max(tracks.len() as i32 - 1, 0);
+            let fixed = sizes.iter().fold(spacing, |a, b| a + b);
+            let free = std::cmp::max(available - fixed, 0) as f32;
+            let total = tracks.iter().fold(0.0, |a, t| match *t { Track::Fraction(v) => a + v, _ => a });
+
+            if total > 0.0 {
+                // Round the running total so the tracks add up to the free space
+                let mut shared = 0.0;
+                for (t, track) in tracks.iter().enumerate() {
+                    if let Track::Fraction(v) = *track {
+                        let start = (free * shared/total).round() as i32;
+                        shared += v;
+                        sizes[t] = (free * shared/total).round() as i32 - start;
+                    }
+                }
+            }
+        }
+
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::Align;
+    use render::RenderArea;
+    use super::*;
+
+    fn item(width: i32, column: usize, column_span: usize) -> GridItem {
+        GridItem {
+            size: [width, 10],
+            cell: GridCell { column_span: column_span, .. GridCell::new(0, column) }
+        }
+    }
+
+    #[test]
+    fn parse_reads_tracks() {
+        assert_eq!(Track::parse("100"), Some(Track::Pixels(100)));
+        assert_eq!(Track::parse("100px"), Some(Track::Pixels(100)));
+        assert_eq!(Track::parse("1.5fr"), Some(Track::Fraction(1.5)));
+        assert_eq!(Track::parse("auto"), Some(Track::Auto));
+        assert_eq!(Track::parse_list("auto 1fr 0"), Some(vec![Track::Auto, Track::Fraction(1.0), Track::Pixels(0)]));
+    }
+
+    #[test]
+    fn parse_rejects_negative_and_invalid_tracks() {
+        assert_eq!(Track::parse("-10"), None);
+        assert_eq!(Track::parse("-10px"), None);
+        assert_eq!(Track::parse("-1fr"), None);
+        assert_eq!(Track::parse("wide"), None);
+        assert_eq!(Track::parse_list("auto -10"), None);
+    }
+
+    #[test]
+    fn track_sizes_fits_auto_tracks_and_shares_the_rest() {
+        let grid = Grid::new(vec![Track::Pixels(50), Track::Fraction(1.0), Track::Auto], Vec::new());
+        let items = [item(30, 2, 1), item(500, 0, 1)];
+        assert_eq!(grid.track_sizes(0, Some(200), &items), vec![50, 120, 30]);
+
+        // Without a limit fractional tracks fit their items
+        let items = [item(30, 2, 1), item(70, 1, 1)];
+        assert_eq!(grid.track_sizes(0, None, &items), vec![50, 70, 30]);
+    }
+
+    #[test]
+    fn track_sizes_rounds_fractions_to_the_free_space() {
+        let grid = Grid { spacing: [5, 0], .. Grid::new(vec![Track::Fraction(1.0); 3], Vec::new()) };
+        let sizes = grid.track_sizes(0, Some(110), &[]);
+        assert_eq!(sizes, vec![33, 34, 33]);
+        assert_eq!(sizes.iter().fold(0, |a, b| a + b), 100);
+
+        let grid = Grid::new(vec![Track::Fraction(1.0), Track::Fraction(2.0)], Vec::new());
+        assert_eq!(grid.track_sizes(0, Some(100), &[]), vec![33, 67]);
+    }
+
+    #[test]
+    fn track_sizes_only_adds_missing_space_for_spans() {
+        let grid = Grid { spacing: [10, 0], .. Grid::new(vec![Track::Auto, Track::Auto], Vec::new()) };
+
+        // The single column item is fitted first, whatever the order
+        let items = [item(100, 0, 2), item(40, 0, 1)];
+        assert_eq!(grid.track_sizes(0, None, &items), vec![65, 25]);
+
+        // Spans that already fit don't grow their tracks
+        let items = [item(40, 0, 1), item(30, 0, 2)];
+        assert_eq!(grid.track_sizes(0, None, &items), vec![40, 0]);
+
+        // Fixed tracks don't grow, the rest goes to the others
+        let grid = Grid::new(vec![Track::Pixels(60), Track::Auto], Vec::new());
+        assert_eq!(grid.track_sizes(0, None, &[item(100, 0, 2)]), vec![60, 40]);
+    }
+
+    #[test]
+    fn items_beyond_the_declared_tracks_get_auto_tracks() {
+        let grid = Grid { spacing: [5, 5], .. Grid::new(vec![Track::Pixels(50)], Vec::new()) };
+        let items = [item(30, 2, 1), GridItem { size: [10, 20], cell: GridCell::new(1, 0) }];
+        assert_eq!(grid.track_sizes(0, None, &items), vec![50, 0, 30]);
+        assert_eq!(grid.track_sizes(1, None, &items), vec![10, 20]);
+        assert_eq!(grid.measure(&items), [90, 35]);
+    }
+
+    #[test]
+    fn arrange_places_items_in_their_cells() {
+        let grid = Grid {
+            spacing: [10, 5],
+            .. Grid::new(vec![Track::Pixels(50), Track::Fraction(1.0)], vec![Track::Pixels(20), Track::Pixels(30)])
+        };
+        let items = [
+            GridItem { size: [10, 10], cell: GridCell::new(0, 0) },
+            GridItem { size: [10, 10], cell: GridCell { align: [Align::End, Align::Center], .. GridCell::new(1, 1) } },
+            GridItem { size: [10, 10], cell: GridCell { row_span: 2, column_span: 2, .. GridCell::new(0, 0) } }];
+
+        let areas = grid.arrange(&RenderArea { position: [100, 100], size: [200, 55] }, &items);
+        assert_eq!(areas, vec![
+            RenderArea { position: [100, 100], size: [50, 20] },
+            RenderArea { position: [290, 135], size: [10, 10] },
+            RenderArea { position: [100, 100], size: [200, 55] }]);
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the computations layouts use to size and position their children.
+//!
+//! These only work with sizes and areas, so they can be used and tested
+//! without any widgets or a gfx device. Widgets use them in two passes:
+//! first they're measured within `Constraints`, then they're arranged in the
+//! areas their parents computed from those sizes.
+//!
+//! ```
+//! use phosphorus::geometry::{Flex, FlexItem, Justify};
+//! use phosphorus::render::RenderArea;
+//!
+//! let area = RenderArea { position: [0, 0], size: [200, 40] };
+//! let flex = Flex { justify: Justify::SpaceBetween, .. Flex::new() };
+//! let items = [FlexItem::new([40, 20]), FlexItem { grow: 1.0, .. FlexItem::new([40, 20]) }];
+//! let areas = flex.arrange(&area, phosphorus::widget::Direction::Horizontal, 4, &items);
+//! assert_eq!(areas[1], RenderArea { position: [44, 0], size: [156, 40] });
+//! ```
+
+use std;
+use render::RenderArea;
+use widget::Direction;
+
+mod anchor;
+mod flex;
+mod grid;
+
+pub use geometry::anchor::*;
+pub use geometry::flex::*;
+pub use geometry::grid::*;
+
+/// The range of sizes a widget may measure itself at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constraints {
+    pub min: [i32; 2],
+    /// The largest size, `std::i32::MAX` on axes without a limit.
+    pub max: [i32; 2]
+}
+
+impl Constraints {
+    /// Initializes new `Constraints` allowing any size up to `max`.
+    pub fn loose(max: [i32; 2]) -> Constraints {
+        Constraints {
+            min: [0, 0],
+            max: max
+        }
+    }
+
+    /// Initializes new `Constraints` allowing any size.
+    pub fn unbounded() -> Constraints {
+        Constraints::loose([std::i32::MAX, std::i32::MAX])
+    }
+
+    /// Initializes new `Constraints` allowing only one size.
+    pub fn tight(size: [i32; 2]) -> Constraints {
+        Constraints {
+            min: size,
+            max: size
+        }
+    }
+
+    /// Checks if there is a largest size on an axis.
+    pub fn is_bounded(&self, axis: usize) -> bool {
+        self.max[axis] != std::i32::MAX
+    }
+
+    /// Limits a size to the range of these constraints.
+    pub fn constrain(&self, size: [i32; 2]) -> [i32; 2] {
+        [
+            std::cmp::max(self.min[0], std::cmp::min(size[0], self.max[0])),
+            std::cmp::max(self.min[1], std::cmp::min(size[1], self.max[1]))
+        ]
+    }
+
+    /// Gets the constraints for content surrounded by space of a size, such
+    /// as a widget's margin or its padding and border.
+    pub fn shrink(&self, by: [i32; 2]) -> Constraints {
+        let shrink = |v: i32, by: i32| if v == std::i32::MAX { v } else { std::cmp::max(v - by, 0) };
+        Constraints {
+            min: [shrink(self.min[0], by[0]), shrink(self.min[1], by[1])],
+            max: [shrink(self.max[0], by[0]), shrink(self.max[1], by[1])]
+        }
+    }
+}
+
+/// Places items one after another in a direction with `spacing` between
+/// them, returning the area of each. Items keep their size and are placed at
+/// the start of the area on the other axis, reversed directions start at the
+/// end of the area.
+pub fn stack(area: &RenderArea, direction: Direction, spacing: i32, sizes: &[[i32; 2]]) -> Vec<RenderArea> {
+    let axis = direction.axis();
+    let mut offset = 0;
+
+    sizes.iter().map(|size| {
+        let mut position = area.position;
+        position[axis] = if direction.is_reversed() {
+            area.position[axis] + area.size[axis] - offset - size[axis]
+        } else {
+            area.position[axis] + offset
+        };
+
+        offset += size[axis] + spacing;
+        RenderArea { position: position, size: *size }
+    }).collect()
+}
+
+/// How an item is positioned within the space it's given on an axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// The item fills the space, as far as its maximum size allows.
+    Stretch
+}
+
+impl Align {
+    /// Parses an alignment from its name as used in markup and stylesheets, such as `center`.
+    pub fn from_name(name: &str) -> Option<Align> {
+        match name {
+            "start" => Some(Align::Start),
+            "center" => Some(Align::Center),
+            "end" => Some(Align::End),
+            "stretch" => Some(Align::Stretch),
+            _ => None
+        }
+    }
+
+    /// Gets the name of the alignment as used in markup and stylesheets.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Align::Start => "start",
+            Align::Center => "center",
+            Align::End => "end",
+            Align::Stretch => "stretch"
+        }
+    }
+}
+
+/// Aligns an item within `space`, returning its offset from the start of the
+/// space and its size. `clamp` limits stretched sizes to the item's limits.
+fn align<C: Fn(f32) -> f32>(align: Align, space: f32, size: f32, clamp: C) -> (f32, f32) {
+    let size = clamp(size);
+    match align {
+        Align::Start => (0.0, size),
+        Align::Center => ((space - size)/2.0, size),
+        Align::End => (space - size, size),
+        Align::Stretch => (0.0, clamp(space))
+    }
+}
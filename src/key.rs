@@ -0,0 +1,72 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// A key on the keyboard, independent of the windowing library it came from.
+///
+/// Keys name physical keys, the text they produce is raised separately as
+/// `Event::TextInput`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+
+    Escape,
+    Tab,
+    Backspace,
+    Enter,
+    Space,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+
+    Shift,
+    Control,
+    Alt,
+    /// The Windows, Command or Super key.
+    Logo,
+
+    /// A key without a name here, by the code the windowing library gave it.
+    Other(u32)
+}
+
+/// The modifier keys held down while a key is pressed or released.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    pub logo: bool
+}
+
+impl Modifiers {
+    /// Initializes new `Modifiers` with no modifier keys held down.
+    pub fn new() -> Modifiers {
+        Modifiers::default()
+    }
+
+    /// Checks if no modifier keys are held down.
+    pub fn is_empty(&self) -> bool {
+        !(self.shift || self.control || self.alt || self.logo)
+    }
+}
@@ -20,82 +20,364 @@
 //! let root = phosphorus::widget::LayoutBuilder::new()
 //!     .with_background_color([21, 23, 24])
 //!     .build();
-//! let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root);
+//! let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root).unwrap();
 //! ```
 //!
 //! Then you can render it using a gfx `Factory` and `Stream` combination.
 //!
 //! ```
-//! gui.render(&mut factory, &mut stream);
+//! gui.render(&mut factory, &mut stream).unwrap();
 //! ```
 
-extern crate cgmath;
 #[macro_use]
 extern crate gfx;
 extern crate gfx_text;
 extern crate gfx_texture;
+#[cfg(test)]
+extern crate gfx_device_gl;
 
 use gfx::traits::*;
+use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
+use widget::Widget;
 
-mod render;
+mod error;
+mod focus;
+mod key;
+mod tree;
+pub mod geometry;
+pub mod markup;
+pub mod render;
+pub mod style;
 pub mod widget;
 
+pub use error::Error;
+pub use key::{Key, Modifiers};
+
+/// A button on the mouse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8)
+}
+
+/// Input raised in the Gui. Mouse positions are in pixels when raised in the
+/// `Gui`, which passes them on to widgets in the units they're laid out in.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Event {
     MouseMoved([i32; 2]),
-    MouseClick,
-    Placeholder
+    /// A mouse button was pressed at a position.
+    MouseDown(MouseButton, [i32; 2]),
+    /// A mouse button was released at a position.
+    MouseUp(MouseButton, [i32; 2]),
+    /// The mouse wheel was scrolled horizontally and vertically, in lines.
+    MouseWheel([f32; 2]),
+    /// The mouse entered the window.
+    MouseEnter,
+    /// The mouse left the window.
+    MouseLeave,
+    /// A mouse button was pressed and released over the same widget. The
+    /// `Gui` dispatches this to that widget after a `MouseUp`, it shouldn't
+    /// be raised by applications.
+    MouseClick(MouseButton, [i32; 2]),
+    /// A key was pressed, or repeated while held down. This is dispatched to the focused widget.
+    KeyDown(Key, Modifiers),
+    /// A key was released. This is dispatched to the focused widget.
+    KeyUp(Key, Modifiers),
+    /// A character of text was typed. This is dispatched to the focused widget.
+    TextInput(char)
+}
+
+impl Event {
+    /// Gets the position of the mouse in the event, if it has one.
+    pub fn position(&self) -> Option<[i32; 2]> {
+        match *self {
+            Event::MouseMoved(position) |
+            Event::MouseDown(_, position) |
+            Event::MouseUp(_, position) |
+            Event::MouseClick(_, position) => Some(position),
+            _ => None
+        }
+    }
+
+    /// Gets a copy of the event with its position replaced, events without a
+    /// position are copied unchanged.
+    pub fn with_position(&self, position: [i32; 2]) -> Event {
+        match *self {
+            Event::MouseMoved(_) => Event::MouseMoved(position),
+            Event::MouseDown(button, _) => Event::MouseDown(button, position),
+            Event::MouseUp(button, _) => Event::MouseUp(button, position),
+            Event::MouseClick(button, _) => Event::MouseClick(button, position),
+            event => event
+        }
+    }
+}
+
+/// Whether the Gui handled an event, or the application should handle it
+/// too, such as a click that shouldn't also reach the game world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventResult {
+    Consumed,
+    Ignored
+}
+
+impl EventResult {
+    /// Checks if the event was handled.
+    pub fn is_consumed(&self) -> bool {
+        *self == EventResult::Consumed
+    }
+
+    /// Combines the results of raising an event in multiple widgets, which
+    /// handled the event if either of them did.
+    pub fn or(self, other: EventResult) -> EventResult {
+        if self.is_consumed() { self } else { other }
+    }
 }
 
 /// Represents a Gui and provides tools to render it.
 pub struct Gui<R: gfx::Resources, F: gfx::Factory<R> + Clone> {
-    root: widget::Layout<R>,
+    tree: tree::Tree<R, render::RenderData<R, F>>,
+    theme: Option<style::Theme>,
+    stylesheet: style::Stylesheet,
+    scale_policy: render::ScalePolicy,
     render_data: Rc<RefCell<render::RenderData<R, F>>>
 }
 
-impl<R: gfx::Resources, F: gfx::Factory<R> + Clone> Gui<R, F> {
+impl<R: gfx::Resources + 'static, F: gfx::Factory<R> + Clone> Gui<R, F> {
     /// Initializes a new Gui with default values.
-    pub fn new<D: gfx::Device>(device: &mut D, factory: &mut F, root: widget::Layout<R>) -> Gui<R, F>
+    pub fn new<D: gfx::Device>(device: &mut D, factory: &mut F, root: widget::Layout<R>) -> Result<Gui<R, F>, Error>
     {
-        Gui {
-            root: root,
-            render_data: Rc::new(RefCell::new(render::RenderData::new(device, factory)))
-        }
+        let render_data = Rc::new(RefCell::new(try!(render::RenderData::new(device, factory))));
+
+        Ok(Gui {
+            tree: tree::Tree::new(root, render_data.clone()),
+            theme: None,
+            stylesheet: style::Stylesheet::new(),
+            scale_policy: render::ScalePolicy::default(),
+            render_data: render_data
+        })
     }
 
     /// Gets the root layout as immutable.
-    pub fn root(&self) -> &widget::Layout<R> { &self.root }
-    /// Gets the root layout as mutable.
-    pub fn root_mut(&mut self) -> &mut widget::Layout<R> { &mut self.root }
-    /// Sets the root layout.
-    pub fn set_root(&mut self, root: widget::Layout<R>) { self.root = root; }
-
-    /// Raises an event in the Gui.
-    pub fn raise_event<S: gfx::Stream<R>>(&mut self, stream: &S, event: Event) {
+    pub fn root(&self) -> &widget::Layout<R> { self.tree.root() }
+    /// Changes the root layout in a callback, returning its result. Widgets
+    /// can be added, removed and moved, as the Gui finds the hovered and
+    /// focused widgets again afterwards. Buttons held down before the change
+    /// don't click anything when they're released.
+    ///
+    /// ```
+    /// gui.with_root(|root| root.push(TextBuilder::new().with_text("Paused").build_boxed()));
+    /// ```
+    pub fn with_root<T, C: FnOnce(&mut widget::Layout<R>) -> T>(&mut self, change: C) -> T {
+        self.tree.with_root(change)
+    }
+    /// Sets the root layout. Nothing in the new layout is focused, and
+    /// buttons held down before don't click anything when they're released.
+    pub fn set_root(&mut self, root: widget::Layout<R>) { self.tree.set_root(root); }
+
+    /// Gets the theme the widgets are styled with, if there is one.
+    pub fn theme(&self) -> Option<&style::Theme> { self.theme.as_ref() }
+    /// Sets the theme the widgets are styled with, taking effect on the next
+    /// render. The stylesheet's rules take precedence over the theme.
+    pub fn set_theme(&mut self, theme: style::Theme) {
+        self.theme = Some(theme);
+        self.update_cascade();
+    }
+    /// Removes the theme, leaving the widgets styled by only the stylesheet.
+    pub fn clear_theme(&mut self) {
+        self.theme = None;
+        self.update_cascade();
+    }
+
+    /// Gets the stylesheet applied to the widgets.
+    pub fn stylesheet(&self) -> &style::Stylesheet { &self.stylesheet }
+    /// Sets the stylesheet applied to the widgets, taking effect on the next render.
+    pub fn set_stylesheet(&mut self, stylesheet: style::Stylesheet) {
+        self.stylesheet = stylesheet;
+        self.update_cascade();
+    }
+
+    /// Gets the policy deciding how the widgets are scaled to the screen.
+    pub fn scale_policy(&self) -> render::ScalePolicy { self.scale_policy }
+    /// Sets the policy deciding how the widgets are scaled to the screen,
+    /// taking effect on the next render. Widgets are laid out, raise events
+    /// and render text in units scaled by it instead of pixels.
+    pub fn set_scale_policy(&mut self, scale_policy: render::ScalePolicy) {
+        self.scale_policy = scale_policy;
+    }
+
+    fn update_cascade(&mut self) {
+        let cascade = match self.theme {
+            Some(ref theme) => self.stylesheet.clone().with_base(&theme.to_stylesheet()),
+            None => self.stylesheet.clone()
+        };
+        self.tree.set_cascade(cascade);
+    }
+
+    /// Finds a widget in the tree by its id.
+    pub fn find_by_id(&self, id: &str) -> Option<&widget::Widget<R>> { self.tree.find_by_id(id) }
+    /// Finds a widget in the tree by its id as mutable.
+    pub fn find_by_id_mut(&mut self, id: &str) -> Option<&mut widget::Widget<R>> { self.tree.find_by_id_mut(id) }
+
+    /// Finds a widget in the tree by its id and downcasts it to a specific widget type.
+    ///
+    /// ```
+    /// gui.get_mut::<phosphorus::widget::Text<_>>("score").unwrap().set_text("100");
+    /// ```
+    pub fn get<W: Any>(&self, id: &str) -> Option<&W> {
+        self.find_by_id(id).and_then(|w| w.as_any().downcast_ref::<W>())
+    }
+    /// Finds a widget in the tree by its id and downcasts it to a specific widget type as mutable.
+    pub fn get_mut<W: Any>(&mut self, id: &str) -> Option<&mut W> {
+        self.find_by_id_mut(id).and_then(|w| w.as_any_mut().downcast_mut::<W>())
+    }
+
+    /// Gets the widget that receives keyboard input, if there is one.
+    pub fn focused(&self) -> Option<&widget::Widget<R>> { self.tree.focused() }
+
+    /// Gives keyboard input to a widget found by its id. Returns if it was
+    /// focused, which it isn't if it can't be or wasn't found.
+    pub fn focus_by_id(&mut self, id: &str) -> bool { self.tree.focus_by_id(id) }
+
+    /// Removes focus from the focused widget, if there is one.
+    pub fn clear_focus(&mut self) { self.tree.clear_focus(); }
+
+    /// Checks if the mouse is over a widget that consumes mouse events, or
+    /// a mouse button is held down after being pressed on one. The
+    /// application shouldn't handle the mouse itself while this is true.
+    pub fn wants_mouse(&self) -> bool { self.tree.wants_mouse() }
+
+    /// Checks if a widget is focused and receives keyboard input.
+    pub fn wants_keyboard(&self) -> bool { self.tree.wants_keyboard() }
+
+    /// Raises an event in the Gui, with mouse positions in pixels. Returns
+    /// `EventResult::Consumed` if a widget handled it, so the application shouldn't.
+    ///
+    /// Mouse events are dispatched to the widget under the mouse and key and
+    /// text events to the focused widget, passing through its ancestors on the
+    /// way down and back up, see `widget::dispatch`. Clicking a widget focuses
+    /// it, and Tab and Shift-Tab move focus through the widgets in the order
+    /// they're in the tree.
+    pub fn raise_event<S: gfx::Stream<R>>(&mut self, stream: &S, event: Event) -> EventResult {
+        self.raise_event_with(stream, event, &mut ())
+    }
+
+    /// Raises an event in the Gui like `raise_event`, giving callbacks and
+    /// handlers mutable access to the application's state through their
+    /// `EventContext`.
+    ///
+    /// ```
+    /// gui.raise_event_with(&stream, event, &mut game);
+    /// // In a button's callback
+    /// context.app_state::<Game>().unwrap().paused = true;
+    /// ```
+    pub fn raise_event_with<S: gfx::Stream<R>, A: Any>(&mut self, stream: &S, event: Event, app_state: &mut A) -> EventResult {
         let (x, y) = stream.get_output().get_size();
-        let area = render::RenderArea {
-            position: [0, 0],
-            size: [x as i32, y as i32]
+        let screen_size = [x as i32, y as i32];
+
+        // Widgets are hit-tested in units, not pixels
+        let event = match event.position() {
+            Some(position) => event.with_position(self.scale_policy.to_units(screen_size, position)),
+            None => event
         };
 
-        self.root.raise_event(&event, &area);
+        let area = self.scale_policy.area(screen_size);
+        self.tree.raise_event(event, &area, app_state)
     }
 
     /// Renders the Gui to the target stream.
     pub fn render<S: gfx::Stream<R>>(
         &mut self,
-        factory: &mut F, stream: &mut S)
+        factory: &mut F, stream: &mut S) -> Result<(), Error>
     {
-        // Set up a layout area to the whole screen
+        // Set up a layout area to the whole screen, in scaled units
         let (x, y) = stream.get_output().get_size();
-        let area = render::RenderArea {
+        let target = render::RenderArea {
             position: [0, 0],
             size: [x as i32, y as i32]
         };
+        let area = self.scale_policy.area(target.size);
+        let scale = self.scale_policy.scale(target.size);
+
+        // Update the widgets' styles and geometry, the tree may have changed since the last render
+        try!(self.tree.update(&area));
 
         // Actually tell the root layout to render to the data
-        let mut renderer = render::ConcreteRenderer::new(factory, stream, self.render_data.clone(), &area);
-        self.root.render(&mut renderer, &area);
+        let mut renderer = render::ConcreteRenderer::new(factory, stream, self.render_data.clone(), &target, scale);
+        self.tree.root().render(&mut renderer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use geometry::Constraints;
+    use render::RenderArea;
+    use style::{Style, Stylesheet, Theme};
+    use widget::{self, ButtonBuilder, EventContext, Layout, LayoutBuilder, TextBuilder, Widget};
+    use tree::is_covered;
+    use super::*;
+
+    /// Builds a window with a panel containing text at the top, and a button below it.
+    fn window(background: Option<[u8; 3]>) -> Layout<Resources> {
+        let panel = LayoutBuilder::new()
+            .with_class("panel")
+            .with_style(Style { width: Some(200), height: Some(50), .. Style::new() })
+            .with_widget(TextBuilder::new().with_text("Score").build_boxed())
+            .build();
+
+        let mut builder = LayoutBuilder::new()
+            .with_widget(Box::new(panel))
+            .with_widget(ButtonBuilder::new().with_text("Quit").build_boxed());
+        if let Some(color) = background {
+            builder = builder.with_background_color(color);
+        }
+        builder.build()
+    }
+
+    /// Clicks a window laid out with a stylesheet, like the `Gui` does.
+    fn click(root: &mut Layout<Resources>, stylesheet: &Stylesheet, position: [i32; 2]) -> EventResult {
+        stylesheet.apply(root, None);
+        root.measure(&Constraints::tight([200, 200]));
+        root.arrange(RenderArea { position: [0, 0], size: [200, 200] });
+
+        let path = root.widget_at(position).unwrap();
+        let mut app_state = ();
+        let mut context = EventContext::new(&path, Some(position), Modifiers::default(), &mut app_state);
+        widget::dispatch(root, &Event::MouseClick(MouseButton::Left, position), &mut context);
+        if is_covered(root, &path) { EventResult::Consumed } else { context.result() }
+    }
+
+    fn themed(stylesheet: Stylesheet) -> Stylesheet {
+        stylesheet.with_base(&Theme::dark().to_stylesheet())
+    }
+
+    #[test]
+    fn unhandled_clicks_with_a_theme_are_ignored() {
+        let theme = themed(Stylesheet::new());
+        let mut root = window(None);
+        assert_eq!(click(&mut root, &theme, [5, 5]), EventResult::Ignored);
+        assert_eq!(click(&mut root, &theme, [5, 150]), EventResult::Ignored);
+
+        // The theme does give the panel a background
+        assert!(root.child(0).unwrap().properties().computed_style.has_background());
+    }
+
+    #[test]
+    fn the_root_never_covers_the_window() {
+        let theme = themed(Stylesheet::parse("layout { background: #202020; }").unwrap());
+        assert_eq!(click(&mut window(Some([32, 32, 32])), &theme, [5, 150]), EventResult::Ignored);
+    }
+
+    #[test]
+    fn buttons_and_styled_panels_cover_the_window() {
+        let theme = themed(Stylesheet::new());
+        assert_eq!(click(&mut window(None), &theme, [5, 55]), EventResult::Consumed);
+
+        let theme = themed(Stylesheet::parse(".panel { background: #202020; }").unwrap());
+        assert_eq!(click(&mut window(None), &theme, [5, 5]), EventResult::Consumed);
     }
 }
@@ -0,0 +1,313 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use markup::{self, Element, ParseError};
+
+/// A named, reusable piece of markup that expands into a widget subtree.
+///
+/// Parameters are referenced in text, attribute values, ids and classes as
+/// `{{name}}`, and `slot` elements mark where the children of the element
+/// using the component are placed. Children go to the unnamed slot, unless
+/// they select a named one with a `slot` attribute. The content of a `slot`
+/// element is used when no children are given for it.
+///
+/// ```text
+/// layout.dialog(background="#2a2c2e")
+///     text.dialog-title {{title}}
+///     slot
+///     horizontal.dialog-buttons
+///         slot(name="buttons")
+/// ```
+///
+/// Once registered with a `WidgetRegistry` it can be used like any element.
+/// The id, classes and `disabled` and `checked` flags of the element using it
+/// are given to the root of the expanded tree.
+///
+/// ```text
+/// dialog#quit(title="Quit?")
+///     text Are you sure you want to quit?
+///     button#quit-yes(slot="buttons") Yes
+/// ```
+#[derive(Clone, Debug)]
+pub struct Component {
+    name: String,
+    parameters: BTreeMap<String, String>,
+    template: Vec<Element>
+}
+
+impl Component {
+    /// Initializes a new `Component` that expands into the given elements.
+    pub fn new(name: &str, template: Vec<Element>) -> Component {
+        Component {
+            name: String::from(name),
+            parameters: BTreeMap::new(),
+            template: template
+        }
+    }
+
+    /// Initializes a new `Component` from a markup document.
+    pub fn from_markup(name: &str, source: &str) -> Result<Component, ParseError> {
+        Ok(Component::new(name, try!(markup::parse(source))))
+    }
+
+    /// Declares a parameter and the value it has if it isn't given.
+    pub fn with_parameter(mut self, name: &str, default: &str) -> Component {
+        self.parameters.insert(String::from(name), String::from(default));
+        self
+    }
+
+    /// Gets the name the component is used by.
+    pub fn name(&self) -> &str { &self.name }
+    /// Gets the elements the component expands into, before parameters and slots are filled in.
+    pub fn template(&self) -> &[Element] { &self.template }
+
+    /// Expands the component for an element using it, resulting in a single
+    /// element that can be built by a `WidgetRegistry`.
+    pub fn expand(&self, element: &Element) -> Result<Element, ParseError> {
+        // Resolve the parameters, attributes of the element override defaults
+        let mut parameters = self.parameters.clone();
+        let mut flags = Vec::new();
+        for (name, value) in &element.attributes {
+            if !parameters.contains_key(name) {
+                if FORWARDED_FLAGS.contains(&&name[..]) {
+                    flags.push((name.clone(), value.clone()));
+                    continue;
+                }
+                return Err(ParseError::new(element.line, format!(
+                    "unknown parameter \"{}\" for component \"{}\"", name, self.name)));
+            }
+            parameters.insert(name.clone(), value.clone());
+        }
+
+        // Sort the element's children in to the slots they should be placed in
+        let mut slots: HashMap<String, Vec<Element>> = HashMap::new();
+        for child in &element.children {
+            let mut child = child.clone();
+            let slot = child.attributes.remove("slot").unwrap_or(String::new());
+            if !has_slot(&self.template, &slot) {
+                return Err(ParseError::new(child.line, format!(
+                    "component \"{}\" has no slot \"{}\"", self.name, slot)));
+            }
+            slots.entry(slot).or_insert(Vec::new()).push(child);
+        }
+
+        let mut expanded = try!(expand_elements(&self.template, &parameters, &slots));
+        let mut root = if expanded.len() == 1 {
+            expanded.remove(0)
+        } else {
+            let mut root = Element::new("layout");
+            root.children = expanded;
+            root
+        };
+
+        // The element using the component identifies the expanded tree
+        root.line = element.line;
+        if element.id.is_some() {
+            root.id = element.id.clone();
+        }
+        root.classes.extend(element.classes.iter().cloned());
+        root.attributes.extend(flags);
+
+        Ok(root)
+    }
+}
+
+/// The flags that are given to the root of the expanded tree, unless the
+/// component declares a parameter with their name.
+const FORWARDED_FLAGS: [&'static str; 2] = ["disabled", "checked"];
+
+fn has_slot(elements: &[Element], name: &str) -> bool {
+    elements.iter().any(|e| {
+        if e.tag == "slot" && e.attribute("name").unwrap_or("") == name {
+            return true;
+        }
+        has_slot(&e.children, name)
+    })
+}
+
+fn expand_elements(
+    template: &[Element], parameters: &BTreeMap<String, String>, slots: &HashMap<String, Vec<Element>>
+) -> Result<Vec<Element>, ParseError> {
+    let mut elements = Vec::new();
+
+    for element in template {
+        if element.tag == "slot" {
+            let name = element.attribute("name").unwrap_or("");
+            match slots.get(name) {
+                Some(children) => elements.extend(children.iter().cloned()),
+                None => elements.extend(try!(expand_elements(&element.children, parameters, slots)))
+            }
+            continue;
+        }
+
+        let mut expanded = element.clone();
+        if let Some(ref id) = element.id {
+            expanded.id = Some(try!(substitute(element, id, parameters)));
+        }
+        expanded.classes.clear();
+        for class in &element.classes {
+            let classes = try!(substitute(element, class, parameters));
+            expanded.classes.extend(classes.split_whitespace().map(String::from));
+        }
+        if let Some(ref text) = element.text {
+            expanded.text = Some(try!(substitute(element, text, parameters)));
+        }
+        for (name, value) in &element.attributes {
+            expanded.attributes.insert(name.clone(), try!(substitute(element, value, parameters)));
+        }
+        expanded.children = try!(expand_elements(&element.children, parameters, slots));
+
+        elements.push(expanded);
+    }
+
+    Ok(elements)
+}
+
+/// Replaces all `{{name}}` references in a value with their parameter.
+fn substitute(element: &Element, value: &str, parameters: &BTreeMap<String, String>) -> Result<String, ParseError> {
+    let mut result = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+
+        let end = match rest[start..].find("}}") {
+            Some(v) => start + v,
+            None => return Err(ParseError::new(element.line, String::from("unclosed parameter reference")))
+        };
+
+        let name = rest[start + 2..end].trim();
+        match parameters.get(name) {
+            Some(v) => result.push_str(v),
+            None => return Err(ParseError::new(element.line, format!("unknown parameter \"{}\"", name)))
+        }
+
+        rest = &rest[end + 2..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use gfx_texture::Texture;
+    use markup::{self, Element, WidgetRegistry};
+    use render::TextureLoader;
+    use widget::Text;
+    use Error;
+    use super::*;
+
+    /// Fails to load every texture, so widgets can be built without a device.
+    struct NoTextures;
+
+    impl TextureLoader<Resources> for NoTextures {
+        fn load_texture(&mut self, source: &str) -> Result<Texture<Resources>, Error> {
+            Err(Error::Texture(String::from(source)))
+        }
+    }
+
+    fn using(source: &str) -> Element {
+        markup::parse(source).unwrap().remove(0)
+    }
+
+    #[test]
+    fn expand_substitutes_parameters() {
+        let component = Component::from_markup("card", concat!(
+            "layout.card(class=\"{{kind}}\")\n",
+            "    text {{title}}\n",
+            "    button(id=\"{{id}}-button\") OK"))
+            .unwrap()
+            .with_parameter("title", "Untitled")
+            .with_parameter("kind", "plain")
+            .with_parameter("id", "ok");
+        let mut registry = WidgetRegistry::<Resources, NoTextures>::new();
+        registry.register_component(component);
+
+        let widget = registry.build(&using("card#main.wide(title=\"Hello\", kind=\"warning\")"), &mut NoTextures).unwrap();
+        assert_eq!(widget.tag(), "layout");
+        assert_eq!(widget.properties().id, Some(String::from("main")));
+        assert_eq!(widget.properties().classes,
+            vec![String::from("card"), String::from("warning"), String::from("wide")]);
+
+        let title = widget.child(0).unwrap().as_any().downcast_ref::<Text<Resources>>().unwrap();
+        assert_eq!(title.text(), "Hello");
+        assert_eq!(widget.child(1).unwrap().properties().id, Some(String::from("ok-button")));
+    }
+
+    #[test]
+    fn expand_forwards_state_flags() {
+        let component = Component::from_markup("toggle", "button {{label}}").unwrap().with_parameter("label", "");
+        let expanded = component.expand(&using("toggle(label=\"Sound\", disabled, checked)")).unwrap();
+        assert_eq!(expanded.attribute("disabled"), Some("true"));
+        assert_eq!(expanded.attribute("checked"), Some("true"));
+        assert!(expanded.attribute("label").is_none());
+
+        // A component can still take a parameter named like a flag
+        let component = Component::from_markup("toggle", "button {{checked}}").unwrap().with_parameter("checked", "");
+        let expanded = component.expand(&using("toggle(checked=\"Yes\")")).unwrap();
+        assert_eq!(expanded.text, Some(String::from("Yes")));
+        assert!(expanded.attribute("checked").is_none());
+    }
+
+    #[test]
+    fn expand_wraps_multiple_elements_in_a_layout() {
+        let component = Component::from_markup("pair", "text a\ntext b").unwrap();
+        let expanded = component.expand(&using("pair")).unwrap();
+        assert_eq!(expanded.tag, "layout");
+        assert_eq!(expanded.children.len(), 2);
+    }
+
+    #[test]
+    fn expand_rejects_unknown_parameters() {
+        let component = Component::from_markup("card", "text {{title}}").unwrap().with_parameter("title", "");
+        let error = component.expand(&using("\n\ncard(color=\"red\")")).unwrap_err();
+        assert_eq!(error.line, 3);
+    }
+
+    #[test]
+    fn expand_fills_slots() {
+        let component = Component::from_markup("dialog", concat!(
+            "layout\n",
+            "    slot\n",
+            "    horizontal\n",
+            "        slot(name=\"buttons\")\n",
+            "            button Close")).unwrap();
+
+        let expanded = component.expand(&using(concat!(
+            "dialog\n",
+            "    text Sure?\n",
+            "    button(slot=\"buttons\") Yes\n",
+            "    button(slot=\"buttons\") No"))).unwrap();
+        assert_eq!(expanded.children[0].text, Some(String::from("Sure?")));
+        let buttons = &expanded.children[1].children;
+        assert_eq!(buttons.len(), 2);
+        assert_eq!(buttons[0].text, Some(String::from("Yes")));
+        assert!(buttons[0].attribute("slot").is_none());
+
+        // A slot without children keeps its own content
+        let expanded = component.expand(&using("dialog")).unwrap();
+        assert_eq!(expanded.children.len(), 1);
+        assert_eq!(expanded.children[0].children[0].text, Some(String::from("Close")));
+    }
+
+    #[test]
+    fn expand_rejects_unknown_slots() {
+        let component = Component::from_markup("dialog", "layout\n    slot").unwrap();
+        let error = component.expand(&using("dialog\n    text(slot=\"footer\") Hi")).unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+}
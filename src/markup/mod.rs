@@ -0,0 +1,98 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains a loader that builds widget trees from Jade-style markup.
+//!
+//! ```text
+//! image(src="./examples/assets/test.png")
+//! text Hello World
+//! text.
+//!     Hello Line #1
+//!     Hello Line #2
+//! button#click-me Click Me!
+//! ```
+//!
+//! A document consisting of a single `layout` element uses that element as
+//! the root layout, allowing a `background` color to be set on it. Layouts
+//! place their children in a `direction`, such as `horizontal` or
+//! `vertical-reverse`, with optional `spacing` between them. Layouts can be
+//! nested, and given the `clip` flag to cut off children extending outside.
+//!
+//! The `flex` flag places a layout's children in flexible lines, configured
+//! by the `justify`, `align`, `wrap` and `line-spacing` attributes, as in
+//! `layout(flex, justify="space-between", align="center")`.
+//!
+//! A `grid` places its children in `columns` and `rows`, listed as sizes in
+//! pixels, fractions such as `1fr`, or `auto`. Children choose their cells
+//! with the `row`, `column`, `row-span` and `column-span` attributes, and
+//! their alignment in them with `halign` and `valign`.
+//!
+//! ```text
+//! grid(columns="auto 1fr", spacing="8 4")
+//!     text(row="0", column="0") Volume
+//!     button(row="0", column="1", halign="end") Mute
+//! ```
+//!
+//! An `anchor-layout` pins its children at distances from its `left`,
+//! `right`, `top` and `bottom` edges, stretching them when both edges on an
+//! axis are given. `center-x` and `center-y` center them with an offset, and
+//! the `center` flag centers them on both axes.
+//!
+//! ```text
+//! anchor-layout
+//!     image#minimap(src="map.png", right="8", top="8", width="128", height="128")
+//!     text#health(left="8", bottom="8") 100
+//! ```
+//!
+//! Any element can be given the `disabled` and `checked` flags to set the
+//! widget's initial state, as in `button#save(disabled) Save`. Its id and
+//! classes can also be set with the `id` and `class` attributes.
+//!
+//! Elements are turned into widgets by a `WidgetRegistry`, which custom
+//! widgets and reusable `Component`s can be registered with.
+//!
+//! A `ScreenWatcher` can be used to reload a markup file into a Gui while the
+//! application is running.
+
+use gfx;
+use render;
+use widget;
+use Error;
+
+mod component;
+mod parser;
+mod registry;
+mod watcher;
+mod writer;
+
+pub use markup::component::*;
+pub use markup::parser::*;
+pub use markup::registry::*;
+pub use markup::watcher::*;
+pub use markup::writer::*;
+
+/// Parses a markup document and builds a layout from it, loading any images it refers to.
+pub fn load_layout<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    source: &str, factory: &mut F
+) -> Result<widget::Layout<R>, Error> {
+    load_layout_with(source, &WidgetRegistry::new(), factory)
+}
+
+/// Parses a markup document and builds a layout from it using the widgets in a registry.
+pub fn load_layout_with<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    source: &str, registry: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<widget::Layout<R>, Error> {
+    let elements = try!(parse(source));
+    registry.build_root(&elements, factory)
+}
@@ -0,0 +1,446 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use std::collections::BTreeMap;
+
+/// Represents an error encountered while parsing markup.
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    /// The column on the line the error was found at, starting at 1, if it's known.
+    pub column: Option<usize>,
+    pub message: String
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: String) -> ParseError {
+        ParseError {
+            line: line,
+            column: None,
+            message: message
+        }
+    }
+
+    /// Initializes a new `ParseError` found at a column of a line.
+    pub fn at(line: usize, column: usize, message: String) -> ParseError {
+        ParseError {
+            line: line,
+            column: Some(column),
+            message: message
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn description(&self) -> &str { &self.message }
+}
+
+/// Represents a single element in a markup document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element {
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub attributes: BTreeMap<String, String>,
+    pub text: Option<String>,
+    pub children: Vec<Element>,
+
+    /// The line this element was declared on, starting at 1.
+    pub line: usize
+}
+
+impl Element {
+    /// Initializes a new `Element` without any content.
+    pub fn new(tag: &str) -> Element {
+        Element {
+            tag: String::from(tag),
+            id: None,
+            classes: Vec::new(),
+            attributes: BTreeMap::new(),
+            text: None,
+            children: Vec::new(),
+            line: 0
+        }
+    }
+
+    /// Gets the value of an attribute, if it's set.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|v| &v[..])
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    content: &'a str
+}
+
+impl<'a> Line<'a> {
+    /// Gets the column of a byte offset in the line's content, starting at 1.
+    fn column(&self, offset: usize) -> usize {
+        self.indent + self.content[..offset].chars().count() + 1
+    }
+}
+
+/// Parses a markup document into its top-level elements.
+pub fn parse(source: &str) -> Result<Vec<Element>, ParseError> {
+    let mut lines = Vec::new();
+    for (i, raw) in source.lines().enumerate() {
+        let content = raw.trim_left();
+
+        // Blank lines and comments don't contribute to the tree
+        if content.is_empty() || content.starts_with("//") {
+            continue;
+        }
+
+        lines.push(Line {
+            number: i + 1,
+            indent: raw.len() - content.len(),
+            content: content.trim_right()
+        });
+    }
+
+    let mut position = 0;
+    let elements = try!(parse_siblings(source, &lines, &mut position, 0));
+
+    if position < lines.len() {
+        let line = &lines[position];
+        return Err(ParseError::at(line.number, line.indent + 1, String::from("unexpected indentation")));
+    }
+
+    Ok(elements)
+}
+
+fn parse_siblings(
+    source: &str, lines: &[Line], position: &mut usize, indent: usize
+) -> Result<Vec<Element>, ParseError> {
+    let mut elements = Vec::new();
+
+    while *position < lines.len() && lines[*position].indent == indent {
+        let line = &lines[*position];
+        *position += 1;
+
+        let (mut element, block_text) = try!(parse_element(line));
+
+        if block_text {
+            // All lines indented deeper than the element are its text content
+            let text_lines = text_block(source, line.number, indent);
+            element.text = Some(text_lines.join("\n"));
+            while *position < lines.len() && lines[*position].indent > indent {
+                *position += 1;
+            }
+        } else if *position < lines.len() && lines[*position].indent > indent {
+            let child_indent = lines[*position].indent;
+            element.children = try!(parse_siblings(source, lines, position, child_indent));
+        }
+
+        elements.push(element);
+    }
+
+    Ok(elements)
+}
+
+fn text_block(source: &str, number: usize, indent: usize) -> Vec<String> {
+    // Text blocks are read from the source directly, so that comment-like
+    // lines and blank lines inside of them are kept intact
+    let mut text_lines: Vec<&str> = Vec::new();
+    let mut text_indent = None;
+    for raw in source.lines().skip(number) {
+        let content = raw.trim_left();
+        let line_indent = raw.len() - content.len();
+
+        if content.is_empty() {
+            text_lines.push("");
+            continue;
+        }
+        if line_indent <= indent {
+            break;
+        }
+
+        // The first line of the block decides how much indentation to strip
+        let cut = match text_indent {
+            Some(v) => std::cmp::min(v, line_indent),
+            None => { text_indent = Some(line_indent); line_indent }
+        };
+        text_lines.push(raw[cut..].trim_right());
+    }
+
+    // Blank lines before the next element aren't part of the text
+    while text_lines.last() == Some(&"") {
+        text_lines.pop();
+    }
+
+    text_lines.iter().map(|l| String::from(*l)).collect()
+}
+
+fn parse_element(line: &Line) -> Result<(Element, bool), ParseError> {
+    let content = line.content;
+    let error = |offset: usize, message: String| Err(ParseError::at(line.number, line.column(offset), message));
+
+    let tag_end = content.find(|c: char| !is_name_char(c)).unwrap_or(content.len());
+    if tag_end == 0 {
+        return error(0, String::from("expected an element name"));
+    }
+
+    let mut element = Element::new(&content[..tag_end]);
+    element.line = line.number;
+
+    let mut rest = &content[tag_end..];
+    loop {
+        if rest.is_empty() {
+            return Ok((element, false));
+        }
+
+        if rest == "." {
+            // A trailing dot means the indented block below is text
+            return Ok((element, true));
+        }
+
+        let offset = content.len() - rest.len();
+        let first = rest.chars().next().unwrap();
+        match first {
+            '#' | '.' => {
+                let name_end = rest[1..].find(|c: char| !is_name_char(c)).map(|i| i + 1).unwrap_or(rest.len());
+                if name_end == 1 {
+                    return error(offset, String::from("expected a name after '#' or '.'"));
+                }
+
+                let name = String::from(&rest[1..name_end]);
+                if first == '#' {
+                    if element.id.is_some() {
+                        return error(offset, String::from("an element can only have one id"));
+                    }
+                    element.id = Some(name);
+                } else {
+                    element.classes.push(name);
+                }
+                rest = &rest[name_end..];
+            },
+            '(' => {
+                let consumed = match parse_attributes(&rest[1..], &mut element.attributes) {
+                    Ok(v) => v,
+                    Err((i, message)) => return error(offset + 1 + i, message)
+                };
+                rest = &rest[consumed + 1..];
+
+                // The id and classes can also be given as attributes, which
+                // unlike the shorthand allows any characters in them
+                if let Some(id) = element.attributes.remove("id") {
+                    if element.id.is_some() {
+                        return error(offset, String::from("an element can only have one id"));
+                    }
+                    element.id = Some(id);
+                }
+                if let Some(classes) = element.attributes.remove("class") {
+                    element.classes.extend(classes.split_whitespace().map(String::from));
+                }
+            },
+            ' ' | '\t' => {
+                element.text = Some(String::from(rest.trim()));
+                return Ok((element, false));
+            },
+            c => return error(offset, format!("unexpected character '{}'", c))
+        }
+    }
+}
+
+/// Parses an attribute list up to and including the closing parenthesis,
+/// returns the amount of bytes consumed. Errors come with the offset in
+/// `source` they were found at.
+fn parse_attributes(source: &str, attributes: &mut BTreeMap<String, String>) -> Result<usize, (usize, String)> {
+    let mut chars = source.char_indices().peekable();
+
+    loop {
+        // Skip separators between attributes
+        while let Some(&(_, c)) = chars.peek() {
+            if c == ',' || c.is_whitespace() { chars.next(); } else { break; }
+        }
+
+        let (start, c) = match chars.next() {
+            Some(v) => v,
+            None => return Err((source.len(), String::from("unclosed attribute list")))
+        };
+        if c == ')' {
+            return Ok(start + 1);
+        }
+        if !is_name_char(c) {
+            return Err((start, format!("unexpected character '{}' in attribute list", c)));
+        }
+
+        // Read the attribute's name
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_name_char(c) { break; }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let name = String::from(&source[start..end]);
+
+        if chars.peek().map(|&(_, c)| c) != Some('=') {
+            // Attributes without a value are flags
+            attributes.insert(name, String::from("true"));
+            continue;
+        }
+        chars.next();
+
+        // Read the attribute's value, either quoted or up to the next separator
+        let mut value = String::new();
+        match chars.peek().map(|&(_, c)| c) {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                let quote_start = chars.next().unwrap().0;
+                let mut closed = false;
+                while let Some((_, c)) = chars.next() {
+                    if c == quote { closed = true; break; }
+                    if c == '\\' {
+                        match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => {}
+                        }
+                        continue;
+                    }
+                    value.push(c);
+                }
+                if !closed {
+                    return Err((quote_start, format!("unclosed string in attribute \"{}\"", name)));
+                }
+            },
+            _ => {
+                while let Some(&(_, c)) = chars.peek() {
+                    if c == ',' || c == ')' || c.is_whitespace() { break; }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+
+        attributes.insert(name, value);
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_one(source: &str) -> Element {
+        let mut elements = parse(source).unwrap();
+        assert_eq!(elements.len(), 1);
+        elements.remove(0)
+    }
+
+    #[test]
+    fn parse_nests_elements_by_indentation() {
+        let elements = parse(concat!(
+            "layout\n",
+            "    horizontal\n",
+            "        button a\n",
+            "\n",
+            "        // A comment\n",
+            "        button b\n",
+            "    text c\n",
+            "text d")).unwrap();
+        assert_eq!(elements.len(), 2);
+
+        let layout = &elements[0];
+        assert_eq!(layout.children.len(), 2);
+        assert_eq!(layout.children[0].tag, "horizontal");
+        assert_eq!(layout.children[0].children.len(), 2);
+        assert_eq!(layout.children[0].children[1].text, Some(String::from("b")));
+        assert_eq!(layout.children[0].children[1].line, 6);
+        assert_eq!(layout.children[1].text, Some(String::from("c")));
+        assert_eq!(elements[1].line, 8);
+    }
+
+    #[test]
+    fn parse_reads_attributes() {
+        let element = parse_one("image(src=\"a b.png\", alt='it\\'s', width=200 height=100, clip)");
+        assert_eq!(element.attribute("src"), Some("a b.png"));
+        assert_eq!(element.attribute("alt"), Some("it's"));
+        assert_eq!(element.attribute("width"), Some("200"));
+        assert_eq!(element.attribute("height"), Some("100"));
+        assert_eq!(element.attribute("clip"), Some("true"));
+    }
+
+    #[test]
+    fn parse_reads_ids_and_classes() {
+        let element = parse_one("button#quit.danger.wide Quit");
+        assert_eq!(element.id, Some(String::from("quit")));
+        assert_eq!(element.classes, vec![String::from("danger"), String::from("wide")]);
+        assert_eq!(element.text, Some(String::from("Quit")));
+
+        let element = parse_one("button.danger(id=\"quit:now\", class=\"wide  tall\")");
+        assert_eq!(element.id, Some(String::from("quit:now")));
+        assert_eq!(element.classes,
+            vec![String::from("danger"), String::from("wide"), String::from("tall")]);
+        assert!(element.attributes.is_empty());
+
+        assert!(parse("button#a(id=\"b\")").is_err());
+    }
+
+    #[test]
+    fn parse_reads_text() {
+        let element = parse_one("text.title   Hello World  ");
+        assert_eq!(element.text, Some(String::from("Hello World")));
+
+        let element = parse_one(concat!(
+            "text.\n",
+            "    Hello Line #1\n",
+            "\n",
+            "      // Not a comment\n",
+            "\n"));
+        assert_eq!(element.classes.len(), 0);
+        assert_eq!(element.text, Some(String::from("Hello Line #1\n\n  // Not a comment")));
+        assert!(element.children.is_empty());
+    }
+
+    #[test]
+    fn parse_reports_bad_indentation() {
+        let error = parse("layout\n        text a\n    text b").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.column, Some(5));
+        assert_eq!(error.to_string(), "line 3, column 5: unexpected indentation");
+    }
+
+    #[test]
+    fn parse_reports_unterminated_strings() {
+        let error = parse("text\n  image(width=2, src=\"hello.png)").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, Some(22));
+
+        let error = parse("image(src=\"hello.png\"").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert_eq!(error.column, Some(22));
+    }
+
+    #[test]
+    fn parse_reports_unexpected_characters() {
+        let error = parse("  \nbutton!").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, Some(7));
+    }
+}
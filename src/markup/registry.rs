@@ -0,0 +1,605 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use gfx;
+use geometry;
+use markup::{Component, Element, ParseError};
+use render;
+use widget;
+use Error;
+
+/// A function that builds a widget from an element description.
+pub type WidgetFactory<R, F> =
+    Box<Fn(&Element, &WidgetRegistry<R, F>, &mut F) -> Result<Box<widget::Widget<R>>, Error>>;
+
+/// Builds widgets from element descriptions, using factories registered by tag name.
+///
+/// ```
+/// let mut registry = phosphorus::markup::WidgetRegistry::new();
+/// registry.register("healthbar", |element, _, _| {
+///     Ok(HealthBar::new(element.attribute("max").unwrap_or("100")))
+/// });
+/// ```
+pub struct WidgetRegistry<R: gfx::Resources, F: render::TextureLoader<R>> {
+    factories: HashMap<String, WidgetFactory<R, F>>,
+    /// The registered components, kept to find components that use themselves.
+    components: HashMap<String, Component>
+}
+
+impl<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static> WidgetRegistry<R, F> {
+    /// Initializes a new `WidgetRegistry` with the built-in widgets registered.
+    pub fn new() -> WidgetRegistry<R, F> {
+        let mut registry = WidgetRegistry::empty();
+        registry.register("text", build_text);
+        registry.register("image", build_image);
+        registry.register("button", build_button);
+        registry.register("layout", build_layout);
+        registry.register("horizontal", build_layout);
+        registry.register("grid", build_grid);
+        registry.register("anchor-layout", build_anchor_layout);
+        registry
+    }
+
+    /// Initializes a new `WidgetRegistry` without any widgets registered.
+    pub fn empty() -> WidgetRegistry<R, F> {
+        WidgetRegistry {
+            factories: HashMap::new(),
+            components: HashMap::new()
+        }
+    }
+
+    /// Registers a factory for a tag, replacing any factory previously registered for it.
+    pub fn register<C>(&mut self, tag: &str, factory: C) where
+        C: Fn(&Element, &WidgetRegistry<R, F>, &mut F) -> Result<Box<widget::Widget<R>>, Error> + 'static
+    {
+        self.components.remove(tag);
+        self.factories.insert(String::from(tag), Box::new(factory));
+    }
+
+    /// Registers a component under its name, elements using it are built from its expansion.
+    ///
+    /// A component may not use itself, directly or through other components,
+    /// building an element using such a component fails instead.
+    pub fn register_component(&mut self, component: Component) {
+        let name = String::from(component.name());
+        let template = component.clone();
+        self.register(&name, move |element, registry, factory| {
+            try!(registry.check_component(component.name(), element.line));
+            let expanded = try!(component.expand(element));
+            registry.build(&expanded, factory)
+        });
+        self.components.insert(name, template);
+    }
+
+    /// Checks if a factory is registered for a tag.
+    pub fn is_registered(&self, tag: &str) -> bool {
+        self.factories.contains_key(tag)
+    }
+
+    /// Builds a widget from an element using the factory registered for its tag.
+    pub fn build(&self, element: &Element, factory: &mut F) -> Result<Box<widget::Widget<R>>, Error> {
+        match self.factories.get(&element.tag) {
+            Some(f) => f(element, self, factory),
+            None => Err(Error::Markup(ParseError::new(
+                element.line, format!("unknown element \"{}\"", element.tag))))
+        }
+    }
+
+    /// Checks that the component registered under a name doesn't use itself,
+    /// which would expand it without end. Errors are reported at `line`.
+    fn check_component(&self, name: &str, line: usize) -> Result<(), ParseError> {
+        let component = match self.components.get(name) {
+            Some(v) => v,
+            None => return Ok(())
+        };
+
+        let mut using = vec![String::from(name)];
+        self.check_uses(component.template(), &mut using, line)
+    }
+
+    /// Checks that none of the elements use a component that's already being
+    /// expanded, following the components they use through their templates.
+    fn check_uses(&self, elements: &[Element], using: &mut Vec<String>, line: usize) -> Result<(), ParseError> {
+        for element in elements {
+            if let Some(component) = self.components.get(&element.tag) {
+                if let Some(start) = using.iter().position(|name| *name == element.tag) {
+                    let mut cycle = using[start..].to_vec();
+                    cycle.push(element.tag.clone());
+                    return Err(ParseError::new(line, format!(
+                        "component \"{}\" uses itself through {}", element.tag, cycle.join(" -> "))));
+                }
+
+                using.push(element.tag.clone());
+                try!(self.check_uses(component.template(), using, line));
+                using.pop();
+            }
+
+            try!(self.check_uses(&element.children, using, line));
+        }
+
+        Ok(())
+    }
+
+    /// Builds widgets from all children of an element.
+    pub fn build_children(&self, element: &Element, factory: &mut F) -> Result<Vec<Box<widget::Widget<R>>>, Error> {
+        let mut widgets = Vec::new();
+        for child in &element.children {
+            widgets.push(try!(self.build(child, factory)));
+        }
+        Ok(widgets)
+    }
+
+    /// Builds a root layout from the top-level elements of a document.
+    ///
+    /// A document consisting of a single `layout` or `horizontal` element uses
+    /// that element as the root layout, otherwise the elements are placed in a
+    /// new layout.
+    pub fn build_root(&self, elements: &[Element], factory: &mut F) -> Result<widget::Layout<R>, Error> {
+        if elements.len() == 1 && (elements[0].tag == "layout" || elements[0].tag == "horizontal") {
+            return layout_builder(&elements[0], self, factory).map(|b| b.build());
+        }
+
+        let mut builder = widget::LayoutBuilder::new();
+        for element in elements {
+            builder = builder.with_widget(try!(self.build(element, factory)));
+        }
+        Ok(builder.build())
+    }
+}
+
+fn build_text<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, _: &WidgetRegistry<R, F>, _: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let mut builder = widget::TextBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    Ok(builder
+        .with_text(element.text.as_ref().map(|t| &t[..]).unwrap_or(""))
+        .build_boxed())
+}
+
+fn build_image<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, _: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let source = match element.attribute("src") {
+        Some(v) => v,
+        None => return Err(Error::Markup(ParseError::new(element.line, String::from("image is missing a \"src\" attribute"))))
+    };
+
+    let mut builder = widget::ImageBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    // Images are shown at their own size, unless the element overrides it
+    let mut image = try!(builder.with_source(source).build_boxed(factory));
+    if element.attribute("width").is_some() || element.attribute("height").is_some() {
+        let size = try!(parse_size(element, image.size()));
+        image.set_size(size);
+    }
+    Ok(image)
+}
+
+fn build_button<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, _: &WidgetRegistry<R, F>, _: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let mut builder = widget::ButtonBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    Ok(builder
+        .with_text(element.text.as_ref().map(|t| &t[..]).unwrap_or(""))
+        .with_size(try!(parse_size(element, [80, 20])))
+        .build_boxed())
+}
+
+fn build_layout<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, registry: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let builder = try!(layout_builder(element, registry, factory));
+    Ok(Box::new(builder.build()))
+}
+
+fn layout_builder<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, registry: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<widget::LayoutBuilder<R>, Error> {
+    let mut builder = widget::LayoutBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    if let Some(v) = element.attribute("background") {
+        builder = builder.with_background_color(try!(parse_color(element, v)));
+    }
+
+    // A horizontal element is a layout that's horizontal unless told otherwise
+    let default_direction = if element.tag == "horizontal" { "horizontal" } else { "vertical" };
+    let direction = element.attribute("direction").unwrap_or(default_direction);
+    builder = builder.with_direction(match widget::Direction::from_name(direction) {
+        Some(v) => v,
+        None => return Err(Error::Markup(ParseError::new(element.line, format!("invalid direction \"{}\"", direction))))
+    });
+
+    builder = builder.with_clipping(is_set(element, "clip"));
+
+    if let Some(v) = element.attribute("spacing") {
+        builder = builder.with_spacing(match v.parse() {
+            Ok(v) => v,
+            Err(_) => return Err(Error::Markup(ParseError::new(element.line, format!("invalid spacing \"{}\"", v))))
+        });
+    }
+
+    if is_set(element, "flex") {
+        builder = builder.with_flex(try!(parse_flex(element)));
+    }
+
+    for widget in try!(registry.build_children(element, factory)) {
+        builder = builder.with_widget(widget);
+    }
+
+    Ok(builder)
+}
+
+fn build_grid<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, registry: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let mut builder = widget::GridBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    let invalid = |name: &str, value: &str|
+        Err(Error::Markup(ParseError::new(element.line, format!("invalid {} \"{}\"", name, value))));
+
+    if let Some(v) = element.attribute("columns") {
+        builder = builder.with_columns(match geometry::Track::parse_list(v) {
+            Some(v) => v,
+            None => return invalid("columns", v)
+        });
+    }
+    if let Some(v) = element.attribute("rows") {
+        builder = builder.with_rows(match geometry::Track::parse_list(v) {
+            Some(v) => v,
+            None => return invalid("rows", v)
+        });
+    }
+    if let Some(v) = element.attribute("spacing") {
+        // A single value is used for both columns and rows
+        let mut values = Vec::new();
+        for part in v.split_whitespace() {
+            match part.parse() {
+                Ok(v) => values.push(v),
+                Err(_) => return invalid("spacing", v)
+            }
+        }
+        builder = builder.with_spacing(match values.len() {
+            1 => [values[0], values[0]],
+            2 => [values[0], values[1]],
+            _ => return invalid("spacing", v)
+        });
+    }
+
+    // Children describe their cells with attributes meant for the grid, not for themselves
+    for child in &element.children {
+        let cell = try!(parse_cell(child));
+        let child = without_attributes(child, &CELL_ATTRIBUTES);
+        builder = builder.with_widget(try!(registry.build(&child, factory)), cell);
+    }
+
+    Ok(Box::new(builder.build()))
+}
+
+/// The attributes of a grid's child element that describe the cells it's placed in.
+const CELL_ATTRIBUTES: [&'static str; 6] = ["row", "column", "row-span", "column-span", "halign", "valign"];
+
+/// Reads the cells a child of a grid is placed in from its attributes.
+fn parse_cell(element: &Element) -> Result<geometry::GridCell, ParseError> {
+    let invalid = |name: &str, value: &str|
+        Err(ParseError::new(element.line, format!("invalid {} \"{}\"", name, value)));
+    let mut cell = geometry::GridCell::new(0, 0);
+
+    {
+        let mut numbers = [
+            ("row", &mut cell.row), ("column", &mut cell.column),
+            ("row-span", &mut cell.row_span), ("column-span", &mut cell.column_span)];
+        for &mut (name, ref mut target) in numbers.iter_mut() {
+            if let Some(v) = element.attribute(name) {
+                **target = match v.parse() {
+                    Ok(v) => v,
+                    Err(_) => return invalid(name, v)
+                };
+            }
+        }
+    }
+
+    for (axis, name) in ["halign", "valign"].iter().enumerate() {
+        if let Some(v) = element.attribute(name) {
+            cell.align[axis] = match geometry::Align::from_name(v) {
+                Some(v) => v,
+                None => return invalid(name, v)
+            };
+        }
+    }
+
+    Ok(cell)
+}
+
+fn build_anchor_layout<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+    element: &Element, registry: &WidgetRegistry<R, F>, factory: &mut F
+) -> Result<Box<widget::Widget<R>>, Error> {
+    let mut builder = widget::AnchorLayoutBuilder::new();
+    apply_common(element, builder.properties_mut());
+
+    for child in &element.children {
+        let anchors = try!(parse_anchors(child));
+        let child = without_attributes(child, &ANCHOR_ATTRIBUTES);
+        builder = builder.with_widget(try!(registry.build(&child, factory)), anchors);
+    }
+
+    Ok(Box::new(builder.build()))
+}
+
+/// The attributes of an anchor layout's child element that describe its anchors.
+const ANCHOR_ATTRIBUTES: [&'static str; 7] = ["left", "right", "top", "bottom", "center-x", "center-y", "center"];
+
+/// Reads the anchors pinning a child of an anchor layout from its attributes.
+/// The `center` flag centers it on both axes where no other anchor is given.
+fn parse_anchors(element: &Element) -> Result<geometry::Anchors, ParseError> {
+    let centered = is_set(element, "center");
+    Ok(geometry::Anchors::new(
+        try!(parse_anchor(element, ["left", "right", "center-x"], centered)),
+        try!(parse_anchor(element, ["top", "bottom", "center-y"], centered))))
+}
+
+/// Reads the anchor on one axis from the start, end and center attributes named.
+fn parse_anchor(element: &Element, names: [&str; 3], centered: bool) -> Result<geometry::Anchor, ParseError> {
+    let mut offsets = [None; 3];
+    for (i, name) in names.iter().enumerate() {
+        if let Some(v) = element.attribute(name) {
+            offsets[i] = match v.parse::<i32>() {
+                Ok(v) => Some(v),
+                Err(_) => return Err(ParseError::new(element.line, format!("invalid {} \"{}\"", name, v)))
+            };
+        }
+    }
+
+    match (offsets[0], offsets[1], offsets[2]) {
+        (None, None, None) if centered => Ok(geometry::Anchor::Center(0)),
+        (None, None, None) => Ok(geometry::Anchor::Start(0)),
+        (Some(start), None, None) => Ok(geometry::Anchor::Start(start)),
+        (None, Some(end), None) => Ok(geometry::Anchor::End(end)),
+        (Some(start), Some(end), None) => Ok(geometry::Anchor::Stretch(start, end)),
+        (None, None, Some(center)) => Ok(geometry::Anchor::Center(center)),
+        _ => Err(ParseError::new(element.line, format!(
+            "\"{}\" can't be combined with \"{}\" or \"{}\"", names[2], names[0], names[1])))
+    }
+}
+
+/// Copies an element without the attributes its parent reads from it.
+fn without_attributes(element: &Element, names: &[&str]) -> Element {
+    let mut element = element.clone();
+    for name in names {
+        element.attributes.remove(*name);
+    }
+    element
+}
+
+/// Reads the options of a flex layout from its `justify`, `align`, `wrap`
+/// and `line-spacing` attributes.
+fn parse_flex(element: &Element) -> Result<geometry::Flex, Error> {
+    let invalid = |name: &str, value: &str|
+        Err(Error::Markup(ParseError::new(element.line, format!("invalid {} \"{}\"", name, value))));
+    let mut flex = geometry::Flex::new();
+
+    if let Some(v) = element.attribute("justify") {
+        flex.justify = match geometry::Justify::from_name(v) {
+            Some(v) => v,
+            None => return invalid("justify", v)
+        };
+    }
+    if let Some(v) = element.attribute("align") {
+        flex.align = match geometry::Align::from_name(v) {
+            Some(v) => v,
+            None => return invalid("align", v)
+        };
+    }
+    flex.wrap = is_set(element, "wrap");
+    if let Some(v) = element.attribute("line-spacing") {
+        flex.line_spacing = match v.parse() {
+            Ok(v) => v,
+            Err(_) => return invalid("line-spacing", v)
+        };
+    }
+
+    Ok(flex)
+}
+
+/// Gives a widget the id and classes of its element, and the state set by
+/// its `disabled` and `checked` flags.
+fn apply_common(element: &Element, properties: &mut widget::Properties) {
+    properties.id = element.id.clone();
+    properties.classes.extend(element.classes.iter().cloned());
+    properties.state.disabled = is_set(element, "disabled");
+    properties.state.checked = is_set(element, "checked");
+}
+
+/// Checks if a flag attribute such as `disabled` is set on an element.
+fn is_set(element: &Element, name: &str) -> bool {
+    element.attribute(name) == Some("true")
+}
+
+/// Reads the optional `width` and `height` attributes of an element.
+pub fn parse_size(element: &Element, default: [i32; 2]) -> Result<[i32; 2], ParseError> {
+    let mut size = default;
+    for (i, name) in ["width", "height"].iter().enumerate() {
+        if let Some(v) = element.attribute(name) {
+            size[i] = match v.parse() {
+                Ok(v) => v,
+                Err(_) => return Err(ParseError::new(element.line, format!("invalid {} \"{}\"", name, v)))
+            };
+        }
+    }
+
+    Ok(size)
+}
+
+/// Reads a color in the `#rrggbb` format.
+pub fn parse_color(element: &Element, value: &str) -> Result<[u8; 3], ParseError> {
+    let error = || Err(ParseError::new(element.line, format!("invalid color \"{}\"", value)));
+
+    if value.len() != 7 || !value.starts_with("#") || !value[1..].chars().all(|c| c.is_digit(16)) {
+        return error();
+    }
+
+    let mut color = [0; 3];
+    for i in 0..3 {
+        color[i] = match u8::from_str_radix(&value[1 + i*2..3 + i*2], 16) {
+            Ok(v) => v,
+            Err(_) => return error()
+        };
+    }
+
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use gfx_texture::Texture;
+    use markup::{self, Component};
+    use render::TextureLoader;
+    use widget::{self, Widget};
+    use Error;
+    use super::*;
+
+    /// Fails to load every texture, so widgets can be built without a device.
+    struct NoTextures;
+
+    impl TextureLoader<Resources> for NoTextures {
+        fn load_texture(&mut self, source: &str) -> Result<Texture<Resources>, Error> {
+            Err(Error::Texture(String::from(source)))
+        }
+    }
+
+    fn registry(components: &[(&str, &str)]) -> WidgetRegistry<Resources, NoTextures> {
+        let mut registry = WidgetRegistry::new();
+        for &(name, source) in components {
+            registry.register_component(Component::from_markup(name, source).unwrap());
+        }
+        registry
+    }
+
+    fn build(registry: &WidgetRegistry<Resources, NoTextures>, source: &str) -> Result<Box<Widget<Resources>>, Error> {
+        registry.build(&markup::parse(source).unwrap()[0], &mut NoTextures)
+    }
+
+    fn build_error(registry: &WidgetRegistry<Resources, NoTextures>, source: &str) -> Error {
+        match build(registry, source) {
+            Ok(widget) => panic!("built a {} from \"{}\"", widget.tag(), source),
+            Err(e) => e
+        }
+    }
+
+    #[test]
+    fn built_in_tags_build_their_widgets() {
+        let registry = registry(&[]);
+        let cases = [
+            ("text#a.big(disabled) Hello", "text"),
+            ("button#a.big(disabled, width=100) OK", "button"),
+            ("layout#a.big(disabled, spacing=4)", "layout"),
+            ("horizontal#a.big(disabled)", "layout"),
+            ("grid#a.big(disabled, columns=\"1fr 2fr\")", "grid"),
+            ("anchor-layout#a.big(disabled)\n    text(left=4) Hi", "anchor-layout")];
+
+        for &(source, tag) in cases.iter() {
+            let widget = build(&registry, source).unwrap();
+            assert_eq!(widget.tag(), tag);
+            assert_eq!(widget.properties().id, Some(String::from("a")));
+            assert_eq!(widget.properties().classes, vec![String::from("big")]);
+            assert!(widget.properties().state.disabled);
+            assert!(!widget.properties().state.checked);
+        }
+
+        let button = build(&registry, "button(checked, width=100) OK").unwrap();
+        let button = button.as_any().downcast_ref::<widget::Button<Resources>>().unwrap();
+        assert!(button.properties().state.checked);
+        assert_eq!(button.size(), [100, 20]);
+    }
+
+    #[test]
+    fn images_need_a_source_that_loads() {
+        let registry = registry(&[]);
+        match build_error(&registry, "\nimage(width=10)") {
+            Error::Markup(e) => assert_eq!(e.line, 2),
+            e => panic!("unexpected error {:?}", e)
+        }
+        match build_error(&registry, "image(src=\"missing.png\")") {
+            Error::Texture(source) => assert_eq!(source, "missing.png"),
+            e => panic!("unexpected error {:?}", e)
+        }
+    }
+
+    #[test]
+    fn registered_tags_build_with_their_factory() {
+        let mut registry = registry(&[]);
+        registry.register("label", |element, _, _| Ok(widget::TextBuilder::new()
+            .with_id(element.attribute("name").unwrap_or(""))
+            .build_boxed()));
+        assert!(registry.is_registered("label"));
+
+        let widget = build(&registry, "label(name=\"score\")").unwrap();
+        assert_eq!(widget.tag(), "text");
+        assert_eq!(widget.id(), Some("score"));
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let registry = registry(&[]);
+        match build_error(&registry, "layout\n    text a\n    healthbar") {
+            Error::Markup(e) => {
+                assert_eq!(e.line, 3);
+                assert_eq!(e.message, "unknown element \"healthbar\"");
+            },
+            e => panic!("unexpected error {:?}", e)
+        }
+    }
+
+    #[test]
+    fn components_using_other_components_are_accepted() {
+        let registry = registry(&[("label", "text {{value}}"), ("row", "horizontal\n    label\n    slot")]);
+        assert!(registry.check_component("row", 1).is_ok());
+    }
+
+    #[test]
+    fn component_using_itself_is_rejected() {
+        let registry = registry(&[("row", "horizontal\n    text a\n    row")]);
+        let error = registry.check_component("row", 4).unwrap_err();
+        assert_eq!(error.line, 4);
+        assert!(error.message.contains("row -> row"));
+    }
+
+    #[test]
+    fn components_using_each_other_are_rejected() {
+        let registry = registry(&[("a", "layout\n    b"), ("b", "layout\n    text\n    a")]);
+        assert!(registry.check_component("a", 1).unwrap_err().message.contains("a -> b -> a"));
+        assert!(registry.check_component("b", 1).unwrap_err().message.contains("b -> a -> b"));
+    }
+
+    #[test]
+    fn cycles_through_slot_content_in_templates_are_rejected() {
+        let registry = registry(&[("a", "b\n    a"), ("b", "layout\n    slot")]);
+        assert!(registry.check_component("a", 1).is_err());
+    }
+
+    #[test]
+    fn replacing_a_component_forgets_its_template() {
+        let mut registry = registry(&[("row", "horizontal\n    row")]);
+        registry.register("row", |_, _, _| Err(::Error::Shader(String::new())));
+        assert!(registry.check_component("row", 1).is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+use gfx;
+use markup;
+use render;
+use widget;
+use Error;
+use Gui;
+
+/// Watches a markup file and reloads it into a Gui whenever it changes.
+///
+/// ```
+/// let mut watcher = phosphorus::markup::ScreenWatcher::new("./screen.jade")
+///     .with_callback("click-me", |_| println!("Hello"));
+/// let root = watcher.load(&mut factory).unwrap();
+/// let mut gui = phosphorus::Gui::new(&mut device, &mut factory, root).unwrap();
+///
+/// loop {
+///     if let Err(e) = watcher.poll(&mut gui, &mut factory) {
+///         println!("{}", e);
+///     }
+///     // ...
+/// }
+/// ```
+pub struct ScreenWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    polled: bool,
+    callbacks: HashMap<String, Rc<RefCell<FnMut(&mut widget::EventContext)>>>
+}
+
+impl ScreenWatcher {
+    /// Initializes a new `ScreenWatcher` for the markup file at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> ScreenWatcher {
+        ScreenWatcher {
+            path: path.as_ref().to_path_buf(),
+            modified: None,
+            polled: false,
+            callbacks: HashMap::new()
+        }
+    }
+
+    /// Requests a callback to be attached to the button with the given id
+    /// every time the file is loaded. The buttons of all loads share it.
+    pub fn with_callback<C: FnMut(&mut widget::EventContext) + 'static>(mut self, id: &str, callback: C) -> ScreenWatcher {
+        self.callbacks.insert(String::from(id), Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Gets the path of the watched file.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Loads the watched file into a new layout.
+    pub fn load<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+        &mut self, factory: &mut F
+    ) -> Result<widget::Layout<R>, Error> {
+        self.load_with(&markup::WidgetRegistry::new(), factory)
+    }
+
+    /// Loads the watched file into a new layout using the widgets in a registry.
+    pub fn load_with<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+        &mut self, registry: &markup::WidgetRegistry<R, F>, factory: &mut F
+    ) -> Result<widget::Layout<R>, Error> {
+        self.modified = self.modified_time();
+        self.polled = true;
+
+        let mut source = String::new();
+        let mut file = try!(fs::File::open(&self.path));
+        try!(file.read_to_string(&mut source));
+
+        let mut layout = try!(markup::load_layout_with(&source, registry, factory));
+        self.attach_callbacks(&mut layout);
+        Ok(layout)
+    }
+
+    /// Reloads the watched file into the Gui if it changed since it was last
+    /// loaded, returns if the root layout was replaced.
+    ///
+    /// If the file can't be loaded the Gui keeps its current layout, and the
+    /// error is returned once until the file changes again.
+    pub fn poll<R: gfx::Resources + 'static, F: gfx::Factory<R> + Clone + 'static>(
+        &mut self, gui: &mut Gui<R, F>, factory: &mut F
+    ) -> Result<bool, Error> {
+        if !self.is_changed() {
+            return Ok(false);
+        }
+
+        self.poll_with(gui, &markup::WidgetRegistry::new(), factory)
+    }
+
+    /// Reloads the watched file into the Gui using the widgets in a registry
+    /// if it changed since it was last loaded, see `poll`.
+    pub fn poll_with<R: gfx::Resources + 'static, F: gfx::Factory<R> + Clone + 'static>(
+        &mut self, gui: &mut Gui<R, F>, registry: &markup::WidgetRegistry<R, F>, factory: &mut F
+    ) -> Result<bool, Error> {
+        match try!(self.reload(registry, factory)) {
+            Some(layout) => {
+                gui.set_root(layout);
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Loads the watched file into a new layout using the widgets in a
+    /// registry if it changed since it was last loaded, see `poll`.
+    pub fn reload<R: gfx::Resources + 'static, F: render::TextureLoader<R> + 'static>(
+        &mut self, registry: &markup::WidgetRegistry<R, F>, factory: &mut F
+    ) -> Result<Option<widget::Layout<R>>, Error> {
+        if !self.is_changed() {
+            return Ok(None);
+        }
+
+        self.load_with(registry, factory).map(Some)
+    }
+
+    fn is_changed(&self) -> bool {
+        !self.polled || self.modified_time() != self.modified
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    fn attach_callbacks<R: gfx::Resources + 'static>(&self, layout: &mut widget::Layout<R>) {
+        for (id, callback) in &self.callbacks {
+            let button = layout.find_by_id_mut(id)
+                .and_then(|w| w.as_any_mut().downcast_mut::<widget::Button<R>>());
+
+            if let Some(button) = button {
+                let callback = callback.clone();
+                button.set_callback(Box::new(move |context| (&mut *callback.borrow_mut())(context)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+    use gfx_device_gl::Resources;
+    use gfx_texture::Texture;
+    use markup::WidgetRegistry;
+    use render::TextureLoader;
+    use widget::Widget;
+    use Error;
+    use super::*;
+
+    /// Fails to load every texture, so widgets can be built without a device.
+    struct NoTextures;
+
+    impl TextureLoader<Resources> for NoTextures {
+        fn load_texture(&mut self, source: &str) -> Result<Texture<Resources>, Error> {
+            Err(Error::Texture(String::from(source)))
+        }
+    }
+
+    /// Writes a file, waiting until its modified time changes if it already exists.
+    fn write_file(path: &Path, source: &str) {
+        let before = fs::metadata(path).and_then(|m| m.modified()).ok();
+        loop {
+            fs::File::create(path).and_then(|mut f| f.write_all(source.as_bytes())).unwrap();
+            if fs::metadata(path).and_then(|m| m.modified()).ok() != before {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("phosphorus-watcher-{}.jade", name))
+    }
+
+    fn reload(watcher: &mut ScreenWatcher) -> Result<Option<widget::Layout<Resources>>, Error> {
+        watcher.reload(&WidgetRegistry::new(), &mut NoTextures)
+    }
+
+    #[test]
+    fn reload_rebuilds_once_per_edit() {
+        let path = temp_path("edit");
+        write_file(&path, "text a");
+        let mut watcher = ScreenWatcher::new(&path);
+
+        assert!(reload(&mut watcher).unwrap().unwrap().child(1).is_none());
+        assert!(reload(&mut watcher).unwrap().is_none());
+
+        write_file(&path, "text a\ntext b");
+        assert!(reload(&mut watcher).unwrap().unwrap().child(1).is_some());
+        assert!(reload(&mut watcher).unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reload_reports_parse_errors_once() {
+        let path = temp_path("parse-error");
+        write_file(&path, "text a");
+        let mut watcher = ScreenWatcher::new(&path);
+        assert!(reload(&mut watcher).unwrap().is_some());
+
+        // The current root stays in place until the file is fixed
+        write_file(&path, "text(a=\"b");
+        match reload(&mut watcher) {
+            Err(Error::Markup(e)) => assert_eq!(e.line, 1),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("expected a markup error")
+        }
+        assert!(reload(&mut watcher).unwrap().is_none());
+
+        write_file(&path, "text b");
+        assert!(reload(&mut watcher).unwrap().is_some());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use markup::Element;
+
+static INDENT: &'static str = "    ";
+
+/// Writes elements as a markup document that can be parsed back in.
+pub fn write(elements: &[Element]) -> String {
+    let mut out = String::new();
+    for element in elements {
+        write_element(element, 0, &mut out);
+    }
+    out
+}
+
+impl Element {
+    /// Writes this element and its children as a markup document.
+    pub fn to_markup(&self) -> String {
+        let mut out = String::new();
+        write_element(self, 0, &mut out);
+        out
+    }
+}
+
+fn write_element(element: &Element, depth: usize, out: &mut String) {
+    write_indent(depth, out);
+    out.push_str(&element.tag);
+
+    // Ids and classes the shorthand can't hold are written as attributes
+    let mut attributes: Vec<String> = Vec::new();
+    if let Some(ref id) = element.id {
+        if is_name(id) {
+            out.push('#');
+            out.push_str(id);
+        } else {
+            attributes.push(format!("id=\"{}\"", escape(id)));
+        }
+    }
+    let mut other_classes: Vec<&str> = Vec::new();
+    for class in &element.classes {
+        if is_name(class) {
+            out.push('.');
+            out.push_str(class);
+        } else {
+            other_classes.push(class);
+        }
+    }
+    if !other_classes.is_empty() {
+        attributes.push(format!("class=\"{}\"", escape(&other_classes.join(" "))));
+    }
+
+    attributes.extend(element.attributes.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))));
+    if !attributes.is_empty() {
+        out.push('(');
+        out.push_str(&attributes.join(", "));
+        out.push(')');
+    }
+
+    match element.text {
+        Some(ref text) if text.contains('\n') => {
+            // Multi-line text has to be written as a text block
+            out.push_str(".\n");
+            for line in text.lines() {
+                if !line.is_empty() {
+                    write_indent(depth + 1, out);
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+        },
+        Some(ref text) if !text.is_empty() => {
+            out.push(' ');
+            out.push_str(text);
+            out.push('\n');
+        },
+        _ => out.push('\n')
+    }
+
+    for child in &element.children {
+        write_element(child, depth + 1, out);
+    }
+}
+
+fn write_indent(depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n")
+}
+
+fn is_name(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use markup::{self, Element};
+    use super::*;
+
+    fn element(tag: &str, id: Option<&str>, classes: &[&str], text: Option<&str>) -> Element {
+        let mut element = Element::new(tag);
+        element.id = id.map(String::from);
+        element.classes = classes.iter().map(|c| String::from(*c)).collect();
+        element.text = text.map(String::from);
+        element
+    }
+
+    /// Clears the lines elements were parsed from, which writing doesn't keep.
+    fn without_lines(mut elements: Vec<Element>) -> Vec<Element> {
+        for element in &mut elements {
+            element.line = 0;
+            let children = ::std::mem::replace(&mut element.children, Vec::new());
+            element.children = without_lines(children);
+        }
+        elements
+    }
+
+    fn assert_round_trips(elements: Vec<Element>) {
+        let source = write(&elements);
+        let parsed = markup::parse(&source).unwrap_or_else(|e| panic!("{} in:\n{}", e, source));
+        assert_eq!(without_lines(parsed), elements, "in:\n{}", source);
+    }
+
+    #[test]
+    fn write_round_trips_ids_classes_and_flags() {
+        let mut button = element("button", Some("quit"), &["danger", "wide"], Some("Quit"));
+        button.attributes.insert(String::from("disabled"), String::from("true"));
+        button.attributes.insert(String::from("width"), String::from("120"));
+
+        // Names the shorthand can't hold have to be written as attributes
+        let odd = element("text", Some("main:title"), &["big", "x.y"], None);
+
+        assert_round_trips(vec![button, odd]);
+    }
+
+    #[test]
+    fn write_round_trips_text() {
+        let mut image = element("image", None, &[], None);
+        image.attributes.insert(String::from("alt"), String::from("A \"quoted\"\nback\\slash"));
+
+        assert_round_trips(vec![
+            element("text", None, &[], Some("Say \"hi\" and 'bye'")),
+            element("text", None, &["block"], Some("Line #1\n    // Indented \"line\" #2\n\nLine #4")),
+            image
+        ]);
+    }
+
+    #[test]
+    fn write_round_trips_nested_children() {
+        let mut horizontal = element("horizontal", None, &[], None);
+        horizontal.children.push(element("button", Some("yes"), &[], Some("Yes")));
+        horizontal.children.push(element("button", Some("no"), &[], Some("No")));
+
+        let mut layout = element("layout", Some("dialog"), &[], None);
+        layout.attributes.insert(String::from("background"), String::from("#2a2c2e"));
+        layout.children.push(element("text", None, &[], Some("Are you\nsure?")));
+        layout.children.push(horizontal);
+
+        assert_round_trips(vec![layout.clone()]);
+        assert_eq!(without_lines(markup::parse(&layout.to_markup()).unwrap()), vec![layout]);
+    }
+}
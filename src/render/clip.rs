@@ -0,0 +1,83 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use gfx;
+use gfx::batch::{Batch, BatchData};
+use gfx::traits::*;
+
+/// A stream that restricts everything drawn to it to a scissor rectangle.
+///
+/// Batches other crates build, such as gfx_text's, come with their own draw
+/// state. Drawing them through this stream clips them like our own batches.
+pub struct ClippedStream<'a, S: 'a> {
+    stream: &'a mut S,
+    scissor: (u16, u16, u16, u16)
+}
+
+impl<'a, S> ClippedStream<'a, S> {
+    /// Initializes a new `ClippedStream` drawing to `stream`, restricted to a
+    /// scissor rectangle given as its left, bottom, width and height.
+    pub fn new(stream: &'a mut S, scissor: (u16, u16, u16, u16)) -> ClippedStream<'a, S> {
+        ClippedStream {
+            stream: stream,
+            scissor: scissor
+        }
+    }
+}
+
+impl<'a, R: gfx::Resources, S: Stream<R>> Stream<R> for ClippedStream<'a, S> {
+    type CommandBuffer = S::CommandBuffer;
+    type Output = S::Output;
+
+    fn get_output(&self) -> &S::Output {
+        self.stream.get_output()
+    }
+
+    fn access(&mut self) -> (&mut gfx::Renderer<R, S::CommandBuffer>, &S::Output) {
+        self.stream.access()
+    }
+
+    fn draw<B: Batch<R>>(&mut self, batch: &B) -> Result<(), gfx::DrawError<B::Error>> {
+        let (x, y, width, height) = self.scissor;
+        let state = match batch.get_data() {
+            Ok((_, _, _, state)) => state.clone().scissor(x, y, width, height),
+            // Let the stream report what's wrong with the batch
+            Err(_) => return self.stream.draw(batch)
+        };
+
+        self.stream.draw(&ClippedBatch {
+            batch: batch,
+            state: state
+        })
+    }
+}
+
+/// A batch drawn with another draw state than its own.
+struct ClippedBatch<'a, B: 'a> {
+    batch: &'a B,
+    state: gfx::DrawState
+}
+
+impl<'a, R: gfx::Resources, B: Batch<R>> Batch<R> for ClippedBatch<'a, B> {
+    type Error = B::Error;
+
+    fn get_data(&self) -> Result<BatchData<R>, B::Error> {
+        let (mesh, attributes, slice, _) = try!(self.batch.get_data());
+        Ok((mesh, attributes, slice, &self.state))
+    }
+
+    fn fill_params(&self, storage: &mut gfx::ParamStorage<R>) -> Result<&gfx::device::handle::Program<R>, B::Error> {
+        self.batch.fill_params(storage)
+    }
+}
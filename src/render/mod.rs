@@ -12,14 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Contains the types widgets use to render themselves.
+
 use std;
-use cgmath;
-use cgmath::FixedArray;
 use gfx;
 use gfx::traits::*;
 use gfx_text;
+use gfx_texture;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
+use style;
+use Error;
+
+mod clip;
+mod scale;
+
+pub use render::scale::*;
 
 static FLAT_VERTEX_SRC: &'static [u8] = b"
     #version 150 core
@@ -94,15 +103,45 @@ gfx_parameters!( TexturedParams {
     u_Texture@ texture: gfx::shade::TextureParam<R>,
 });
 
+/// The font size text is rendered at if no other size is requested.
+pub const DEFAULT_FONT_SIZE: u8 = 13;
+/// The color text is rendered with if no other color is requested.
+pub const DEFAULT_TEXT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+static FONT_DATA: &'static [u8] = include_bytes!("../../assets/Roboto-Regular.ttf");
+
+/// An area on the screen relative to the top left, in the units widgets are
+/// laid out in. These are pixels unless the `Gui` has a `ScalePolicy` scaling them.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct RenderArea {
     pub position: [i32; 2],
     pub size: [i32; 2]
 }
 
-pub struct RenderOffset {
-    pub position: [i32; 2]
+impl RenderArea {
+    /// Checks if a point is inside of the area.
+    pub fn contains(&self, point: [i32; 2]) -> bool {
+        point[0] >= self.position[0] && point[1] >= self.position[1] &&
+            point[0] < self.position[0] + self.size[0] && point[1] < self.position[1] + self.size[1]
+    }
+
+    /// Gets the area covered by both this and another area, which is empty if they don't overlap.
+    pub fn intersect(&self, other: &RenderArea) -> RenderArea {
+        let start = [
+            std::cmp::max(self.position[0], other.position[0]),
+            std::cmp::max(self.position[1], other.position[1])];
+        let end = [
+            std::cmp::min(self.position[0] + self.size[0], other.position[0] + other.size[0]),
+            std::cmp::min(self.position[1] + self.size[1], other.position[1] + other.size[1])];
+
+        RenderArea {
+            position: start,
+            size: [std::cmp::max(0, end[0] - start[0]), std::cmp::max(0, end[1] - start[1])]
+        }
+    }
 }
 
+/// Rendering resources shared between frames.
 pub struct RenderData<R: gfx::Resources, F: gfx::Factory<R> + Clone> {
     draw_state: gfx::DrawState,
     sampler: gfx::device::handle::Sampler<R>,
@@ -110,22 +149,27 @@ pub struct RenderData<R: gfx::Resources, F: gfx::Factory<R> + Clone> {
     flat_program: gfx::device::handle::Program<R>,
     textured_program: gfx::device::handle::Program<R>,
 
-    text_renderer: gfx_text::Renderer<R, F>
+    text_renderers: HashMap<u8, gfx_text::Renderer<R, F>>,
+    textures: HashMap<String, gfx_texture::Texture<R>>,
+
+    /// Text renderers are built when they're first needed to measure text,
+    /// which can be before anything is rendered.
+    factory: F
 }
 
 impl<R: gfx::Resources, F: gfx::Factory<R> + Clone> RenderData<R, F> {
-    pub fn new<D: gfx::Device>(device: &mut D, factory: &mut F) -> RenderData<R, F>
+    pub fn new<D: gfx::Device>(device: &mut D, factory: &mut F) -> Result<RenderData<R, F>, Error>
     {
         let mut factory = factory.clone();
 
         // Set up the stuff we'll need to render
         let flat_program = match factory.link_program(FLAT_VERTEX_SRC, FLAT_FRAGMENT_SRC) {
             Ok(v) => v,
-            Err(e) => panic!(format!("{:?}", e))
+            Err(e) => return Err(Error::Shader(format!("{:?}", e)))
         };
         let textured_program = match factory.link_program(TEXTURED_VERTEX_SRC, TEXTURED_FRAGMENT_SRC) {
             Ok(v) => v,
-            Err(e) => panic!(format!("{:?}", e))
+            Err(e) => return Err(Error::Shader(format!("{:?}", e)))
         };
 
         let state = gfx::DrawState::new();
@@ -134,52 +178,188 @@ impl<R: gfx::Resources, F: gfx::Factory<R> + Clone> RenderData<R, F> {
                 gfx::tex::FilterMethod::Bilinear,
                 gfx::tex::WrapMode::Clamp));
 
-        // Set up our text renderer
-        let text_renderer = gfx_text::new(factory.clone())
-            .with_size(13)
-            .with_font_data(include_bytes!("../../assets/Roboto-Regular.ttf"))
-            .build().unwrap();
+        // Set up our text renderer for the default size, other sizes are added when needed
+        let mut text_renderers = HashMap::new();
+        text_renderers.insert(DEFAULT_FONT_SIZE, try!(build_text_renderer(&factory, DEFAULT_FONT_SIZE)));
 
-        RenderData {
+        Ok(RenderData {
             draw_state: state,
             sampler: sampler,
 
             flat_program: flat_program,
             textured_program: textured_program,
 
-            text_renderer: text_renderer
+            text_renderers: text_renderers,
+            textures: HashMap::new(),
+
+            factory: factory
+        })
+    }
+
+    fn text_renderer(&mut self, size: u8) -> Result<&mut gfx_text::Renderer<R, F>, Error> {
+        if !self.text_renderers.contains_key(&size) {
+            let text_renderer = try!(build_text_renderer(&self.factory, size));
+            self.text_renderers.insert(size, text_renderer);
         }
+
+        Ok(self.text_renderers.get_mut(&size).unwrap())
+    }
+
+    fn texture(&mut self, factory: &mut F, source: &str) -> Result<gfx::handle::Texture<R>, Error> {
+        if !self.textures.contains_key(source) {
+            let texture = try!(load_texture(factory, source));
+            self.textures.insert(String::from(source), texture);
+        }
+
+        Ok(self.textures[source].handle())
+    }
+}
+
+impl<R: gfx::Resources, F: gfx::Factory<R> + Clone> TextMeasurer for RenderData<R, F> {
+    fn text_width(&mut self, text: &str, size: u8) -> Result<i32, Error> {
+        // Glyphs scale with the font size, so measuring at the size in units
+        // gives the width in units however far the text is scaled up
+        let text_renderer = try!(self.text_renderer(size));
+        Ok(text_renderer.measure(text).0)
+    }
+}
+
+/// Loads an image file into a texture.
+pub fn load_texture<R: gfx::Resources, F: gfx::Factory<R>>(
+    factory: &mut F, source: &str
+) -> Result<gfx_texture::Texture<R>, Error> {
+    let settings = gfx_texture::Settings::new();
+    match gfx_texture::Texture::from_path(factory, source, &settings) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(Error::Texture(format!("{}: {}", source, e)))
+    }
+}
+
+/// Loads image files into textures for widgets that show them, such as a gfx
+/// `Factory`.
+pub trait TextureLoader<R: gfx::Resources> {
+    /// Loads an image file into a texture.
+    fn load_texture(&mut self, source: &str) -> Result<gfx_texture::Texture<R>, Error>;
+}
+
+impl<R: gfx::Resources, F: gfx::Factory<R>> TextureLoader<R> for F {
+    fn load_texture(&mut self, source: &str) -> Result<gfx_texture::Texture<R>, Error> {
+        load_texture(self, source)
     }
 }
 
+fn build_text_renderer<R: gfx::Resources, F: gfx::Factory<R> + Clone>(
+    factory: &F, size: u8
+) -> Result<gfx_text::Renderer<R, F>, Error> {
+    match gfx_text::new(factory.clone()).with_size(size).with_font_data(FONT_DATA).build() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(Error::Font(format!("{:?}", e)))
+    }
+}
+
+/// An interface widgets use to measure text before it's rendered.
+pub trait TextMeasurer {
+    /// Gets the width of a line of text at a font size, in the units widgets
+    /// are laid out in.
+    fn text_width(&mut self, text: &str, size: u8) -> Result<i32, Error>;
+}
+
+/// An interface widgets use to render themselves.
 pub trait Renderer<R: gfx::Resources> {
-    fn render_rect_flat(&mut self, position: [i32; 2], size: [i32; 2], color: [f32; 3]);
-    fn render_rect_textured(&mut self, position: [i32; 2], size: [i32; 2], texture: gfx::handle::Texture<R>);
-    fn render_text(&mut self, position: [i32; 2], text: &str);
+    /// Renders a rectangle filled with a single color.
+    fn render_rect_flat(&mut self, position: [i32; 2], size: [i32; 2], color: [f32; 3]) -> Result<(), Error>;
+    /// Renders a rectangle with rounded corners filled with a single color.
+    fn render_rect_rounded(&mut self, position: [i32; 2], size: [i32; 2], radius: i32, color: [f32; 3]) -> Result<(), Error>;
+    /// Renders a rectangle filled with a texture.
+    fn render_rect_textured(&mut self, position: [i32; 2], size: [i32; 2], texture: gfx::handle::Texture<R>) -> Result<(), Error>;
+    /// Renders a rectangle filled with an image file, which is loaded the first time it's used.
+    fn render_image(&mut self, position: [i32; 2], size: [i32; 2], source: &str) -> Result<(), Error>;
+    /// Renders a line of text with its top left at `position`.
+    fn render_text(&mut self, position: [i32; 2], text: &str, color: [f32; 3], size: u8) -> Result<(), Error>;
+
+    /// Restricts rendering to an area, within any area rendering was already restricted to.
+    fn push_clip(&mut self, area: &RenderArea);
+    /// Removes the last restriction added by `push_clip`.
+    fn pop_clip(&mut self);
+
+    /// Renders a border along the inside of the edges of a rectangle.
+    fn render_border(&mut self, position: [i32; 2], size: [i32; 2], width: i32, color: [f32; 3]) -> Result<(), Error> {
+        let width = std::cmp::min(width, std::cmp::min(size[0], size[1])/2);
+        let inner_height = size[1] - width*2;
+
+        try!(self.render_rect_flat(position, [size[0], width], color));
+        try!(self.render_rect_flat([position[0], position[1] + size[1] - width], [size[0], width], color));
+        try!(self.render_rect_flat([position[0], position[1] + width], [width, inner_height], color));
+        self.render_rect_flat([position[0] + size[0] - width, position[1] + width], [width, inner_height], color)
+    }
+
+    /// Renders the background, image and border a style gives to a widget's
+    /// box, `background` is used if the style doesn't set a background color.
+    /// A `texture` is shown inside of the padding and border, unless the style
+    /// sets an image to replace it. Borders are drawn with square corners, even
+    /// if the background is rounded.
+    fn render_box(
+        &mut self, position: [i32; 2], size: [i32; 2], style: &style::Style,
+        background: Option<[f32; 3]>, texture: Option<gfx::handle::Texture<R>>) -> Result<(), Error>
+    {
+        if let Some(color) = style.background.or(background) {
+            match style.corner_radius {
+                Some(radius) if radius > 0 => try!(self.render_rect_rounded(position, size, radius, color)),
+                _ => try!(self.render_rect_flat(position, size, color))
+            }
+        }
+
+        let image_area = match texture {
+            Some(_) => style.content_area(position, size, [0; 4]),
+            None => RenderArea { position: position, size: size }
+        };
+        match (&style.image, texture) {
+            (&Some(ref image), _) => try!(self.render_image(image_area.position, image_area.size, image)),
+            (&None, Some(texture)) => try!(self.render_rect_textured(image_area.position, image_area.size, texture)),
+            (&None, None) => {}
+        }
+
+        let border_width = style.border_width.unwrap_or(0);
+        if border_width > 0 {
+            let color = style.border_color.or(style.color).unwrap_or(DEFAULT_TEXT_COLOR);
+            try!(self.render_border(position, size, border_width, color));
+        }
+
+        Ok(())
+    }
 }
 
+/// Renders to a gfx stream.
 pub struct ConcreteRenderer<'a, R: gfx::Resources, F: 'a + gfx::Factory<R> + Clone, S: 'a + Stream<R>> {
     render_data: Rc<RefCell<RenderData<R, F>>>,
     projection_matrix: [[f32; 4]; 4],
+    /// The area of the render target in pixels.
+    target: RenderArea,
+    /// The amount of pixels every unit widgets render with covers.
+    scale: f32,
+    /// The clip areas in pixels.
+    clips: Vec<RenderArea>,
 
     factory: &'a mut F,
     stream: &'a mut S
 }
 
 impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> ConcreteRenderer<'a, R, F, S> {
+    /// Initializes a new `ConcreteRenderer` for a target covering `area` in
+    /// pixels, with every unit widgets render with covering `scale` pixels.
     pub fn new(
         factory: &'a mut F, stream: &'a mut S,
-        render_data: Rc<RefCell<RenderData<R, F>>>, area: &RenderArea
+        render_data: Rc<RefCell<RenderData<R, F>>>, area: &RenderArea, scale: f32
     )-> ConcreteRenderer<'a, R, F, S> {
         // Prepare shared uniform data that never has to change
-        let proj = cgmath::ortho::<f32>(
-            0.0, area.size[0] as f32,
-            area.size[1] as f32, 0.0,
-            1.0, -1.0).into_fixed();
+        let proj = orthographic(area.size[0] as f32, area.size[1] as f32);
 
         ConcreteRenderer {
             render_data: render_data,
             projection_matrix: proj,
+            target: RenderArea { position: [0, 0], size: area.size },
+            scale: scale,
+            clips: Vec::new(),
 
             factory: factory,
             stream: stream
@@ -187,8 +367,35 @@ impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> ConcreteRe
     }
 }
 
+impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> ConcreteRenderer<'a, R, F, S> {
+    /// Gets the position of a point in pixels.
+    fn to_pixels(&self, point: [i32; 2]) -> [i32; 2] {
+        [(point[0] as f32 * self.scale).round() as i32, (point[1] as f32 * self.scale).round() as i32]
+    }
+
+    /// Gets the position and size of a rectangle in pixels. Both corners are
+    /// scaled, so rectangles sharing an edge still do after scaling.
+    fn rect_to_pixels(&self, position: [i32; 2], size: [i32; 2]) -> ([i32; 2], [i32; 2]) {
+        let start = self.to_pixels(position);
+        let end = self.to_pixels([position[0] + size[0], position[1] + size[1]]);
+        (start, [end[0] - start[0], end[1] - start[1]])
+    }
+
+    /// Gets the draw state to render with, restricted to the current clip area.
+    fn draw_state(&self, base: &gfx::DrawState) -> gfx::DrawState {
+        match self.clips.last() {
+            Some(clip) => {
+                let (x, y, width, height) = scissor(self.target.size[1], clip);
+                base.clone().scissor(x, y, width, height)
+            },
+            None => base.clone()
+        }
+    }
+}
+
 impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> Renderer<R> for ConcreteRenderer<'a, R, F, S> {
-    fn render_rect_flat(&mut self, position: [i32; 2], size: [i32; 2], color: [f32; 3]) {
+    fn render_rect_flat(&mut self, position: [i32; 2], size: [i32; 2], color: [f32; 3]) -> Result<(), Error> {
+        let (position, size) = self.rect_to_pixels(position, size);
         let render_data = &self.render_data.borrow();
 
         // Set up the uniform data
@@ -197,8 +404,7 @@ impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> Renderer<R
             _r: std::marker::PhantomData
         };
 
-        let start = [position[0] as u16, position[1] as u16];
-        let end = [position[0] as u16 + size[0] as u16, position[1] as u16 + size[1] as u16];
+        let (start, end) = rect_corners(position, size);
 
         // Create a mesh from the rectangle
         let mut vertices = Vec::<FlatVertex>::new();
@@ -212,11 +418,45 @@ impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> Renderer<R
         let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
 
         // Actually render that mesh
-        let batch = gfx::batch::bind(&render_data.draw_state, &mesh, slice.clone(), &render_data.flat_program, &flat_params);
-        self.stream.draw(&batch).unwrap();
+        let draw_state = self.draw_state(&render_data.draw_state);
+        let batch = gfx::batch::bind(&draw_state, &mesh, slice.clone(), &render_data.flat_program, &flat_params);
+        self.stream.draw(&batch).map_err(|e| Error::Draw(format!("{:?}", e)))
     }
 
-    fn render_rect_textured(&mut self, position: [i32; 2], size: [i32; 2], texture: gfx::handle::Texture<R>) {
+    fn render_rect_rounded(&mut self, position: [i32; 2], size: [i32; 2], radius: i32, color: [f32; 3]) -> Result<(), Error> {
+        let (position, size) = self.rect_to_pixels(position, size);
+        let radius = (radius as f32 * self.scale).round() as i32;
+        let render_data = &self.render_data.borrow();
+
+        // Set up the uniform data
+        let flat_params = FlatParams::<R> {
+            transform: self.projection_matrix.clone(),
+            _r: std::marker::PhantomData
+        };
+
+        // Create a mesh fanning out from the center to the rounded outline
+        let outline = rounded_rect_outline(position, size, radius);
+        let center = [
+            to_u16(position[0] + std::cmp::max(0, size[0])/2),
+            to_u16(position[1] + std::cmp::max(0, size[1])/2)];
+        let mut vertices = Vec::<FlatVertex>::new();
+        for i in 0..outline.len() {
+            let next = outline[(i + 1) % outline.len()];
+            vertices.push(FlatVertex { pos: center, color: color });
+            vertices.push(FlatVertex { pos: next, color: color });
+            vertices.push(FlatVertex { pos: outline[i], color: color });
+        }
+        let mesh = self.factory.create_mesh(&vertices);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+
+        // Actually render that mesh
+        let draw_state = self.draw_state(&render_data.draw_state);
+        let batch = gfx::batch::bind(&draw_state, &mesh, slice.clone(), &render_data.flat_program, &flat_params);
+        self.stream.draw(&batch).map_err(|e| Error::Draw(format!("{:?}", e)))
+    }
+
+    fn render_rect_textured(&mut self, position: [i32; 2], size: [i32; 2], texture: gfx::handle::Texture<R>) -> Result<(), Error> {
+        let (position, size) = self.rect_to_pixels(position, size);
         let render_data = &self.render_data.borrow();
 
         // Set up the uniform data
@@ -226,8 +466,7 @@ impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> Renderer<R
             _r: std::marker::PhantomData
         };
 
-        let start = [position[0] as u16, position[1] as u16];
-        let end = [position[0] as u16 + size[0] as u16, position[1] as u16 + size[1] as u16];
+        let (start, end) = rect_corners(position, size);
 
         // Create a mesh from the rectangle
         let mut vertices = Vec::<TexturedVertex>::new();
@@ -241,17 +480,166 @@ impl<'a, R: gfx::Resources, F: gfx::Factory<R> + Clone, S: Stream<R>> Renderer<R
         let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
 
         // Actually render that mesh
-        let batch = gfx::batch::bind(&render_data.draw_state, &mesh, slice.clone(), &render_data.textured_program, &textured_params);
-        self.stream.draw(&batch).unwrap();
+        let draw_state = self.draw_state(&render_data.draw_state);
+        let batch = gfx::batch::bind(&draw_state, &mesh, slice.clone(), &render_data.textured_program, &textured_params);
+        self.stream.draw(&batch).map_err(|e| Error::Draw(format!("{:?}", e)))
+    }
+
+    fn render_image(&mut self, position: [i32; 2], size: [i32; 2], source: &str) -> Result<(), Error> {
+        let texture = try!(self.render_data.borrow_mut().texture(self.factory, source));
+        self.render_rect_textured(position, size, texture)
     }
 
-    fn render_text(&mut self, position: [i32; 2], text: &str) {
+    fn render_text(&mut self, position: [i32; 2], text: &str, color: [f32; 3], size: u8) -> Result<(), Error> {
+        // Rasterise the text at its size in pixels, so it stays sharp when scaled up
+        let position = self.to_pixels(position);
+        let size = std::cmp::max(1, std::cmp::min((size as f32 * self.scale).round() as i32, 255)) as u8;
+
         let mut render_data = self.render_data.borrow_mut();
-        render_data.text_renderer.add(
+        let text_renderer = try!(render_data.text_renderer(size));
+        text_renderer.add(
             text,
             position,
-            [1.0, 1.0, 1.0, 1.0],
+            [color[0], color[1], color[2], 1.0],
         );
-        render_data.text_renderer.draw_at(self.stream, self.projection_matrix.clone()).unwrap();
+
+        // Text is drawn with gfx_text's own draw state, so the clip is applied to its batches
+        let result = match self.clips.last() {
+            Some(clip) => {
+                let mut stream = clip::ClippedStream::new(self.stream, scissor(self.target.size[1], clip));
+                text_renderer.draw_at(&mut stream, self.projection_matrix.clone())
+            },
+            None => text_renderer.draw_at(self.stream, self.projection_matrix.clone())
+        };
+        result.map_err(|e| Error::Draw(format!("{:?}", e)))
+    }
+
+    fn push_clip(&mut self, area: &RenderArea) {
+        let (position, size) = self.rect_to_pixels(area.position, area.size);
+        let area = RenderArea { position: position, size: size };
+        let clip = match self.clips.last() {
+            Some(current) => current.intersect(&area),
+            None => self.target.intersect(&area)
+        };
+        self.clips.push(clip);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+}
+
+/// Gets the column-major orthographic projection mapping pixels, with the
+/// origin at the top left, to normalized device coordinates.
+fn orthographic(width: f32, height: f32) -> [[f32; 4]; 4] {
+    [
+        [2.0 / width, 0.0, 0.0, 0.0],
+        [0.0, -2.0 / height, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-1.0, 1.0, 0.0, 1.0]
+    ]
+}
+
+/// Narrows a coordinate in pixels to a vertex coordinate, clamping it so it
+/// can't wrap around.
+fn to_u16(v: i32) -> u16 {
+    std::cmp::max(0, std::cmp::min(v, std::u16::MAX as i32)) as u16
+}
+
+/// Gets the top left and bottom right corners of a rectangle in pixels as
+/// vertex coordinates. A negative size is treated as an empty rectangle.
+fn rect_corners(position: [i32; 2], size: [i32; 2]) -> ([u16; 2], [u16; 2]) {
+    let end = [position[0] + std::cmp::max(0, size[0]), position[1] + std::cmp::max(0, size[1])];
+    ([to_u16(position[0]), to_u16(position[1])], [to_u16(end[0]), to_u16(end[1])])
+}
+
+/// Gets the scissor rectangle for a clip area in pixels, as its left, bottom,
+/// width and height. The scissor's origin is at the bottom left of the target.
+fn scissor(target_height: i32, clip: &RenderArea) -> (u16, u16, u16, u16) {
+    // Clips are kept inside of the target, but clamp anyway so values can't wrap around
+    (
+        to_u16(clip.position[0]),
+        to_u16(target_height - clip.position[1] - clip.size[1]),
+        to_u16(clip.size[0]),
+        to_u16(clip.size[1]))
+}
+
+/// The amount of line segments each rounded corner is made of.
+const CORNER_SEGMENTS: usize = 6;
+
+/// Gets the points on the outline of a rectangle with rounded corners,
+/// clockwise starting at the top left corner.
+fn rounded_rect_outline(position: [i32; 2], size: [i32; 2], radius: i32) -> Vec<[u16; 2]> {
+    let size = [std::cmp::max(0, size[0]), std::cmp::max(0, size[1])];
+    let radius = std::cmp::max(0, std::cmp::min(radius, std::cmp::min(size[0], size[1])/2)) as f32;
+    let start = [position[0] as f32, position[1] as f32];
+    let end = [(position[0] + size[0]) as f32, (position[1] + size[1]) as f32];
+
+    // The centers of the corner arcs, with the angle each arc starts at
+    let corners = [
+        ([start[0] + radius, start[1] + radius], 180.0f32),
+        ([end[0] - radius, start[1] + radius], 270.0),
+        ([end[0] - radius, end[1] - radius], 0.0),
+        ([start[0] + radius, end[1] - radius], 90.0)];
+
+    let mut outline = Vec::new();
+    for &(center, start_angle) in corners.iter() {
+        for i in 0..CORNER_SEGMENTS + 1 {
+            let angle = (start_angle + 90.0 * (i as f32)/(CORNER_SEGMENTS as f32)).to_radians();
+            outline.push([
+                to_u16((center[0] + radius*angle.cos()).round() as i32),
+                to_u16((center[1] + radius*angle.sin()).round() as i32)]);
+        }
+    }
+
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use style::Style;
+    use super::{orthographic, rect_corners, rounded_rect_outline, scissor};
+
+    fn area(position: [i32; 2], size: [i32; 2]) -> RenderArea {
+        RenderArea { position: position, size: size }
+    }
+
+    #[test]
+    fn orthographic_maps_corners_to_device_coordinates() {
+        let m = orthographic(200.0, 100.0);
+        let project = |x: f32, y: f32| [
+            m[0][0] * x + m[1][0] * y + m[3][0],
+            m[0][1] * x + m[1][1] * y + m[3][1]
+        ];
+        assert_eq!(project(0.0, 0.0), [-1.0, 1.0]);
+        assert_eq!(project(200.0, 100.0), [1.0, -1.0]);
+    }
+
+    #[test]
+    fn intersect_keeps_the_overlap() {
+        assert_eq!(area([0, 0], [100, 50]).intersect(&area([-20, 10], [60, 100])), area([0, 10], [40, 40]));
+        assert_eq!(area([0, 0], [10, 10]).intersect(&area([20, 20], [10, 10])).size, [0, 0]);
+    }
+
+    #[test]
+    fn scissor_flips_to_the_bottom_left() {
+        assert_eq!(scissor(600, &area([10, 20], [100, 50])), (10, 530, 100, 50));
+    }
+
+    #[test]
+    fn scissor_clamps_instead_of_wrapping() {
+        assert_eq!(scissor(600, &area([-10, -20], [100, 700])), (0, 0, 100, 700));
+        assert_eq!(scissor(600, &area([0, 0], [100_000, 10])), (0, 590, 65535, 10));
+    }
+
+    #[test]
+    fn over_padded_content_renders_as_an_empty_rect() {
+        let style = Style { padding: Some([40; 4]), .. Style::new() };
+        let content = style.content_area([10, 10], [20, 20], [0; 4]);
+        assert_eq!(rect_corners(content.position, content.size), ([50, 50], [50, 50]));
+
+        assert_eq!(rect_corners([-5, 0], [10, -30]), ([0, 0], [5, 0]));
+        assert!(rounded_rect_outline([0, 0], [-20, -20], 5).iter().all(|&p| p == [0, 0]));
     }
 }
@@ -0,0 +1,167 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use render::RenderArea;
+
+/// The DPI at which a scale factor of 1 is used by `ScalePolicy::Dpi`.
+pub const BASE_DPI: f32 = 96.0;
+
+/// The smallest scale factor a policy results in, so the UI never grows to
+/// an unreasonable amount of units.
+const MIN_SCALE: f32 = 0.1;
+
+/// Which dimensions of the screen decide the scale of a reference resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceMode {
+    /// The UI is as wide as the reference, its height follows the screen.
+    MatchWidth,
+    /// The UI is as high as the reference, its width follows the screen.
+    MatchHeight,
+    /// The whole reference fits on the screen, the UI expands beyond it on
+    /// the axis where the screen has more room.
+    Expand
+}
+
+/// How the units widgets are laid out in map to the pixels of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalePolicy {
+    /// Every unit covers a fixed amount of pixels, 1 maps them one to one.
+    Fixed(f32),
+    /// Units scale with the screen's dots per inch, relative to `BASE_DPI`.
+    Dpi(f32),
+    /// The UI is designed for a resolution and scaled to fit the screen.
+    Reference {
+        resolution: [i32; 2],
+        mode: ReferenceMode
+    }
+}
+
+impl ScalePolicy {
+    /// Gets the amount of pixels every unit covers on a screen of a size in pixels.
+    pub fn scale(&self, screen_size: [i32; 2]) -> f32 {
+        let scale = match *self {
+            ScalePolicy::Fixed(scale) => scale,
+            ScalePolicy::Dpi(dpi) => dpi/BASE_DPI,
+            ScalePolicy::Reference { resolution, mode } => {
+                let width = screen_size[0] as f32 / std::cmp::max(resolution[0], 1) as f32;
+                let height = screen_size[1] as f32 / std::cmp::max(resolution[1], 1) as f32;
+                match mode {
+                    ReferenceMode::MatchWidth => width,
+                    ReferenceMode::MatchHeight => height,
+                    ReferenceMode::Expand => width.min(height)
+                }
+            }
+        };
+
+        // Scales that make no sense, such as from a DPI of 0, map pixels one to one
+        if scale > 0.0 { scale.max(MIN_SCALE) } else { 1.0 }
+    }
+
+    /// Gets the area in units that covers a screen of a size in pixels.
+    pub fn area(&self, screen_size: [i32; 2]) -> RenderArea {
+        let scale = self.scale(screen_size);
+        RenderArea {
+            position: [0, 0],
+            size: [
+                (screen_size[0] as f32 / scale).floor() as i32,
+                (screen_size[1] as f32 / scale).floor() as i32]
+        }
+    }
+
+    /// Gets the point in units at a position on the screen in pixels.
+    pub fn to_units(&self, screen_size: [i32; 2], point: [i32; 2]) -> [i32; 2] {
+        let scale = self.scale(screen_size);
+        [(point[0] as f32 / scale).floor() as i32, (point[1] as f32 / scale).floor() as i32]
+    }
+}
+
+impl Default for ScalePolicy {
+    fn default() -> ScalePolicy { ScalePolicy::Fixed(1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFERENCE: [i32; 2] = [800, 600];
+
+    fn reference(mode: ReferenceMode) -> ScalePolicy {
+        ScalePolicy::Reference { resolution: REFERENCE, mode: mode }
+    }
+
+    #[test]
+    fn policies_scale_to_the_screen() {
+        // (policy, screen size, scale, area size)
+        let table = [
+            (ScalePolicy::Fixed(1.0), [800, 600], 1.0, [800, 600]),
+            (ScalePolicy::Fixed(2.0), [801, 600], 2.0, [400, 300]),
+            (ScalePolicy::Fixed(0.0), [800, 600], 1.0, [800, 600]),
+            (ScalePolicy::Fixed(0.01), [80, 60], MIN_SCALE, [800, 600]),
+            (ScalePolicy::Dpi(96.0), [800, 600], 1.0, [800, 600]),
+            (ScalePolicy::Dpi(192.0), [1600, 1200], 2.0, [800, 600]),
+            (ScalePolicy::Dpi(144.0), [300, 150], 1.5, [200, 100]),
+            (ScalePolicy::Dpi(-96.0), [800, 600], 1.0, [800, 600]),
+            (reference(ReferenceMode::MatchWidth), [1600, 900], 2.0, [800, 450]),
+            (reference(ReferenceMode::MatchWidth), [400, 600], 0.5, [800, 1200]),
+            (reference(ReferenceMode::MatchHeight), [1600, 900], 1.5, [1066, 600]),
+            (reference(ReferenceMode::MatchHeight), [800, 300], 0.5, [1600, 600]),
+            (reference(ReferenceMode::Expand), [1600, 900], 1.5, [1066, 600]),
+            (reference(ReferenceMode::Expand), [1600, 2400], 2.0, [800, 1200]),
+            (reference(ReferenceMode::Expand), [800, 600], 1.0, [800, 600]),
+        ];
+
+        for &(policy, screen, scale, size) in &table {
+            assert_eq!(policy.scale(screen), scale, "scale of {:?} on {:?}", policy, screen);
+            let area = policy.area(screen);
+            assert_eq!(area.position, [0, 0]);
+            assert_eq!(area.size, size, "area of {:?} on {:?}", policy, screen);
+        }
+    }
+
+    #[test]
+    fn zero_sized_screens_have_empty_areas() {
+        let policies = [
+            ScalePolicy::Fixed(2.0),
+            ScalePolicy::Dpi(192.0),
+            reference(ReferenceMode::MatchWidth),
+            reference(ReferenceMode::MatchHeight),
+            reference(ReferenceMode::Expand),
+        ];
+
+        for policy in &policies {
+            assert_eq!(policy.area([0, 0]).size, [0, 0], "{:?}", policy);
+            assert!(policy.scale([0, 0]) > 0.0, "{:?}", policy);
+        }
+
+        // A reference can't be matched on a screen without room on an axis
+        assert_eq!(reference(ReferenceMode::MatchWidth).scale([0, 600]), 1.0);
+        assert_eq!(reference(ReferenceMode::MatchHeight).scale([800, 0]), 1.0);
+        assert_eq!(reference(ReferenceMode::Expand).scale([1600, 0]), 1.0);
+        assert_eq!(reference(ReferenceMode::MatchWidth).scale([1600, 0]), 2.0);
+    }
+
+    #[test]
+    fn degenerate_references_are_treated_as_one_pixel() {
+        let policy = ScalePolicy::Reference { resolution: [0, -5], mode: ReferenceMode::MatchHeight };
+        assert_eq!(policy.scale([800, 2]), 2.0);
+    }
+
+    #[test]
+    fn points_are_converted_to_units() {
+        let policy = reference(ReferenceMode::MatchWidth);
+        assert_eq!(policy.to_units([1600, 1200], [801, 3]), [400, 1]);
+        assert_eq!(ScalePolicy::Fixed(1.0).to_units([0, 0], [5, 7]), [5, 7]);
+    }
+}
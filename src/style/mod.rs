@@ -0,0 +1,429 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains styles and the stylesheets that assign them to widgets.
+//!
+//! ```text
+//! /* Rules apply by element name, class and id */
+//! button { background: #474747; padding: 1 4; margin: 2 0; border: 1 #808080; }
+//! .title { color: #e0e0e0; font-size: 18; }
+//! #quit, button.danger { background: #a03030; width: 120; }
+//! .fill { flex-grow: 1; max-width: 300; align-self: center; }
+//! button:hover { background: #575757; }
+//! button:pressed { image: "./assets/button-pressed.png"; }
+//! ```
+//!
+//! When multiple rules set the same property the rule with the most specific
+//! selector wins, counting ids first, then classes, then element names. Rules
+//! that are equally specific are applied in the order they were written in.
+//! The text color and font size are inherited from the parent widget if no
+//! rule sets them, and styles set directly on a widget override all rules.
+//!
+//! Selectors can require the widget to be in an interaction state with the
+//! `:hover`, `:pressed`, `:focus`, `:disabled` and `:checked` pseudo-classes,
+//! which count as classes for specificity.
+//!
+//! Widgets follow a box model, their content is surrounded by padding, then a
+//! border and then a margin separating them from other widgets. Widths and
+//! heights include the padding and border, but not the margin.
+//!
+//! In a flex layout `flex-grow` and `flex-shrink` decide how much widgets
+//! grow and shrink to fill it, within their `min-width`, `max-width`,
+//! `min-height` and `max-height`, and `align-self` overrides how the layout
+//! aligns them.
+//!
+//! A `Theme` provides a base style for all widgets, which stylesheets build on.
+
+use std::cmp;
+use gfx;
+use geometry;
+use render;
+use widget::{self, Interaction};
+
+mod parser;
+mod selector;
+mod theme;
+
+pub use style::parser::*;
+pub use style::selector::*;
+pub use style::theme::*;
+
+/// Visual properties of a widget, unset properties fall back to the widget's own defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    pub background: Option<[f32; 3]>,
+    /// Path to an image drawn over the background, stretched to the widget's size.
+    pub image: Option<String>,
+    pub color: Option<[f32; 3]>,
+    pub font_size: Option<u8>,
+    /// Space between the border and the content, in the order top, right, bottom, left.
+    pub padding: Option<[i32; 4]>,
+    /// Space around the border separating the widget from others, in the order top, right, bottom, left.
+    pub margin: Option<[i32; 4]>,
+    pub border_width: Option<i32>,
+    /// Color of the border, the text color is used if it isn't set.
+    pub border_color: Option<[f32; 3]>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub min_width: Option<i32>,
+    pub min_height: Option<i32>,
+    pub max_width: Option<i32>,
+    pub max_height: Option<i32>,
+    /// Radius of the rounded corners of the background.
+    pub corner_radius: Option<i32>,
+
+    /// The share of free space the widget grows by in a flex layout.
+    pub flex_grow: Option<f32>,
+    /// The share of missing space the widget shrinks by in a flex layout, relative to its size.
+    pub flex_shrink: Option<f32>,
+    /// Overrides how a flex layout aligns the widget on its cross axis.
+    pub align_self: Option<geometry::Align>
+}
+
+impl Style {
+    /// Initializes a new `Style` without any properties set.
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    /// Overrides the properties of this style with all properties set in `other`.
+    pub fn merge(&mut self, other: &Style) {
+        if other.background.is_some() { self.background = other.background; }
+        if other.image.is_some() { self.image = other.image.clone(); }
+        if other.color.is_some() { self.color = other.color; }
+        if other.font_size.is_some() { self.font_size = other.font_size; }
+        if other.padding.is_some() { self.padding = other.padding; }
+        if other.margin.is_some() { self.margin = other.margin; }
+        if other.border_width.is_some() { self.border_width = other.border_width; }
+        if other.border_color.is_some() { self.border_color = other.border_color; }
+        if other.width.is_some() { self.width = other.width; }
+        if other.height.is_some() { self.height = other.height; }
+        if other.min_width.is_some() { self.min_width = other.min_width; }
+        if other.min_height.is_some() { self.min_height = other.min_height; }
+        if other.max_width.is_some() { self.max_width = other.max_width; }
+        if other.max_height.is_some() { self.max_height = other.max_height; }
+        if other.corner_radius.is_some() { self.corner_radius = other.corner_radius; }
+        if other.flex_grow.is_some() { self.flex_grow = other.flex_grow; }
+        if other.flex_shrink.is_some() { self.flex_shrink = other.flex_shrink; }
+        if other.align_self.is_some() { self.align_self = other.align_self; }
+    }
+
+    /// Creates a style with only the properties children inherit.
+    pub fn inherited(&self) -> Style {
+        Style {
+            color: self.color,
+            font_size: self.font_size,
+            .. Style::default()
+        }
+    }
+
+    /// Checks if widgets with this style and `other` are sized and placed the
+    /// same way, differing only in how they look.
+    pub fn has_same_layout(&self, other: &Style) -> bool {
+        self.font_size == other.font_size &&
+            self.padding == other.padding &&
+            self.margin == other.margin &&
+            self.border_width == other.border_width &&
+            self.width == other.width &&
+            self.height == other.height &&
+            self.min_width == other.min_width &&
+            self.min_height == other.min_height &&
+            self.max_width == other.max_width &&
+            self.max_height == other.max_height &&
+            self.flex_grow == other.flex_grow &&
+            self.flex_shrink == other.flex_shrink &&
+            self.align_self == other.align_self
+    }
+
+    /// Checks if this style draws anything behind a widget's content.
+    pub fn has_background(&self) -> bool {
+        self.background.is_some() || self.image.is_some()
+    }
+
+    /// Gets the size set by this style, using `default` for unset dimensions.
+    pub fn size_or(&self, default: [i32; 2]) -> [i32; 2] {
+        [self.width.unwrap_or(default[0]), self.height.unwrap_or(default[1])]
+    }
+
+    /// Gets the combined size of the border and padding on each side, in the
+    /// order top, right, bottom, left. Uses `default_padding` if no padding is set.
+    pub fn insets(&self, default_padding: [i32; 4]) -> [i32; 4] {
+        let padding = self.padding.unwrap_or(default_padding);
+        let border = self.border_width.unwrap_or(0);
+        [padding[0] + border, padding[1] + border, padding[2] + border, padding[3] + border]
+    }
+
+    /// Gets the area inside of the border and padding of a box. The area is
+    /// empty rather than negative if the insets don't fit in the box.
+    pub fn content_area(&self, position: [i32; 2], size: [i32; 2], default_padding: [i32; 4]) -> render::RenderArea {
+        let insets = self.insets(default_padding);
+        render::RenderArea {
+            position: [position[0] + insets[3], position[1] + insets[0]],
+            size: [
+                cmp::max(0, size[0] - insets[1] - insets[3]),
+                cmp::max(0, size[1] - insets[0] - insets[2])
+            ]
+        }
+    }
+}
+
+/// A rule assigning a style to all widgets matching any of its selectors.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub selectors: Vec<Selector>,
+    pub style: Style
+}
+
+/// A list of rules that assign styles to widgets.
+#[derive(Clone, Debug, Default)]
+pub struct Stylesheet {
+    rules: Vec<Rule>,
+    /// The amount of rules at the start that come from a base stylesheet.
+    base_len: usize
+}
+
+impl Stylesheet {
+    /// Initializes a new `Stylesheet` without any rules.
+    pub fn new() -> Stylesheet {
+        Stylesheet::default()
+    }
+
+    /// Gets the rules in this stylesheet, in the order they were added.
+    pub fn rules(&self) -> &[Rule] { &self.rules }
+
+    /// Adds a rule after all existing rules.
+    pub fn add_rule(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Adds all rules of another stylesheet after the existing rules, letting
+    /// them take precedence over equally specific rules in this one.
+    pub fn extend(&mut self, other: &Stylesheet) {
+        self.rules.extend(other.rules.iter().cloned());
+    }
+
+    /// Places the rules of a base stylesheet, such as a theme's, before the
+    /// rules of this one. Rules of this stylesheet take precedence over the
+    /// base's regardless of their specificity.
+    pub fn with_base(mut self, base: &Stylesheet) -> Stylesheet {
+        let mut rules = base.rules.clone();
+        rules.extend(self.rules.drain(..));
+
+        self.base_len = base.rules.len();
+        self.rules = rules;
+        self
+    }
+
+    /// Computes the style of an element, based on its parent's computed style.
+    pub fn compute(
+        &self, tag: &str, properties: &widget::Properties, parent: Option<&Style>
+    ) -> Style {
+        self.cascade(tag, properties, parent).0
+    }
+
+    /// Computes the style of an element like `compute`, and checks if its
+    /// background comes from anything but the base stylesheet.
+    fn cascade(
+        &self, tag: &str, properties: &widget::Properties, parent: Option<&Style>
+    ) -> (Style, bool) {
+        let mut style = parent.map(|p| p.inherited()).unwrap_or(Style::default());
+
+        // Find all matching rules with the specificity they matched with
+        let mut matching = Vec::new();
+        for (order, rule) in self.rules.iter().enumerate() {
+            let specificity = rule.selectors.iter()
+                .filter(|s| s.matches(tag, properties))
+                .map(|s| s.specificity())
+                .max();
+
+            if let Some(specificity) = specificity {
+                matching.push((order >= self.base_len, specificity, order, &rule.style));
+            }
+        }
+
+        // Apply the least important rules first so the others override them
+        matching.sort_by(|a, b| (a.0, a.1, a.2).cmp(&(b.0, b.1, b.2)));
+        let mut styled_background = false;
+        for &(from_stylesheet, _, _, rule_style) in &matching {
+            style.merge(rule_style);
+            styled_background |= from_stylesheet && rule_style.has_background();
+        }
+
+        style.merge(&properties.style);
+        styled_background |= properties.style.has_background();
+
+        // Styles for the states the widget is in override its regular style
+        for &interaction in Interaction::all().iter() {
+            if !properties.state.is(interaction) {
+                continue;
+            }
+
+            for &(ref state, ref state_style) in &properties.state_styles {
+                if *state == interaction {
+                    style.merge(state_style);
+                    styled_background |= state_style.has_background();
+                }
+            }
+        }
+
+        (style, styled_background)
+    }
+
+    /// Computes the styles of a widget and all of its descendants. Widgets
+    /// whose new style changes their size or placement are invalidated.
+    pub fn apply<R: gfx::Resources>(&self, widget: &mut widget::Widget<R>, parent: Option<&Style>) {
+        let (style, styled_background) = self.cascade(widget.tag(), widget.properties(), parent);
+        if !style.has_same_layout(&widget.properties().computed_style) {
+            widget.invalidate();
+        }
+
+        for i in 0..widget.child_count() {
+            if let Some(child) = widget.child_mut(i) {
+                self.apply(child, Some(&style));
+            }
+        }
+
+        widget.properties_mut().computed_style = style;
+        widget.properties_mut().styled_background = styled_background;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use widget::{Interaction, Properties};
+    use super::*;
+
+    fn properties(id: Option<&str>, classes: &[&str]) -> Properties {
+        let mut properties = Properties::new();
+        properties.id = id.map(String::from);
+        properties.classes = classes.iter().map(|c| String::from(*c)).collect();
+        properties
+    }
+
+    fn background(stylesheet: &str, tag: &str, properties: &Properties) -> Option<[f32; 3]> {
+        Stylesheet::parse(stylesheet).unwrap().compute(tag, properties, None).background
+    }
+
+    const RED: [f32; 3] = [1.0, 0.0, 0.0];
+    const GREEN: [f32; 3] = [0.0, 1.0, 0.0];
+    const BLUE: [f32; 3] = [0.0, 0.0, 1.0];
+
+    #[test]
+    fn more_specific_rules_win_regardless_of_order() {
+        let properties = properties(Some("quit"), &["danger"]);
+        let sheet = "#quit { background: #ff0000; } .danger { background: #00ff00; } button { background: #0000ff; }";
+        assert_eq!(background(sheet, "button", &properties), Some(RED));
+
+        let sheet = "button.danger { background: #ff0000; } .danger { background: #00ff00; } button { background: #0000ff; }";
+        assert_eq!(background(sheet, "button", &properties), Some(RED));
+
+        let sheet = "button { background: #0000ff; } .danger { background: #00ff00; }";
+        assert_eq!(background(sheet, "button", &properties), Some(GREEN));
+    }
+
+    #[test]
+    fn later_rules_win_ties() {
+        let properties = properties(None, &["a", "b"]);
+        assert_eq!(background(".a { background: #ff0000; } .b { background: #00ff00; }", "text", &properties), Some(GREEN));
+        assert_eq!(background(".b { background: #00ff00; } .a { background: #ff0000; }", "text", &properties), Some(RED));
+    }
+
+    #[test]
+    fn rules_only_set_the_properties_they_declare() {
+        let style = Stylesheet::parse("button { background: #ff0000; } .wide { width: 100; }").unwrap()
+            .compute("button", &properties(None, &["wide"]), None);
+        assert_eq!(style.background, Some(RED));
+        assert_eq!(style.width, Some(100));
+    }
+
+    #[test]
+    fn state_rules_apply_while_in_the_state() {
+        let stylesheet = Stylesheet::parse(concat!(
+            "button { background: #ff0000; }\n",
+            "button:hover { background: #00ff00; }\n",
+            "button:hover:pressed { background: #0000ff; }")).unwrap();
+        let mut properties = properties(None, &[]);
+        assert_eq!(stylesheet.compute("button", &properties, None).background, Some(RED));
+
+        properties.state.hovered = true;
+        assert_eq!(stylesheet.compute("button", &properties, None).background, Some(GREEN));
+
+        properties.state.pressed = true;
+        assert_eq!(stylesheet.compute("button", &properties, None).background, Some(BLUE));
+    }
+
+    #[test]
+    fn inline_styles_override_rules_and_state_styles_override_both() {
+        let stylesheet = Stylesheet::parse("#quit { background: #ff0000; }").unwrap();
+        let mut properties = properties(Some("quit"), &[]);
+        properties.style.background = Some(GREEN);
+        properties.state_styles.push((Interaction::Pressed, Style { background: Some(BLUE), .. Style::new() }));
+        assert_eq!(stylesheet.compute("button", &properties, None).background, Some(GREEN));
+
+        properties.state.pressed = true;
+        assert_eq!(stylesheet.compute("button", &properties, None).background, Some(BLUE));
+    }
+
+    #[test]
+    fn content_area_is_empty_when_over_padded() {
+        let style = Style { padding: Some([40; 4]), border_width: Some(2), .. Style::new() };
+        let content = style.content_area([0, 0], [20, 20], [0; 4]);
+        assert_eq!(content.position, [42, 42]);
+        assert_eq!(content.size, [0, 0]);
+    }
+
+    #[test]
+    fn only_text_properties_are_inherited() {
+        let parent = Style {
+            background: Some(RED),
+            color: Some(GREEN),
+            font_size: Some(20),
+            padding: Some([4; 4]),
+            width: Some(100),
+            .. Style::new()
+        };
+
+        let style = Stylesheet::new().compute("text", &properties(None, &[]), Some(&parent));
+        assert_eq!(style.color, Some(GREEN));
+        assert_eq!(style.font_size, Some(20));
+        assert_eq!(style.background, None);
+        assert_eq!(style.padding, None);
+        assert_eq!(style.width, None);
+
+        // Rules on the child still override what it inherits
+        let style = Stylesheet::parse("text { color: #0000ff; }").unwrap()
+            .compute("text", &properties(None, &[]), Some(&parent));
+        assert_eq!(style.color, Some(BLUE));
+        assert_eq!(style.font_size, Some(20));
+    }
+
+    #[test]
+    fn base_rules_lose_to_stylesheet_rules() {
+        let base = Stylesheet::parse("#quit.danger { background: #ff0000; color: #ff0000; }").unwrap();
+        let stylesheet = Stylesheet::parse("button { background: #00ff00; }").unwrap().with_base(&base);
+
+        // The stylesheet's rule wins although the base's is more specific
+        let style = stylesheet.compute("button", &properties(Some("quit"), &["danger"]), None);
+        assert_eq!(style.background, Some(GREEN));
+        assert_eq!(style.color, Some(RED));
+        assert_eq!(stylesheet.rules().len(), 2);
+    }
+
+    #[test]
+    fn has_same_layout_ignores_colors() {
+        let style = Style { background: Some(RED), width: Some(10), .. Style::new() };
+        assert!(style.has_same_layout(&Style { background: Some(GREEN), .. style.clone() }));
+        assert!(!style.has_same_layout(&Style { width: Some(11), .. style.clone() }));
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use geometry::Align;
+use markup::ParseError;
+use style::{Rule, Selector, Style, Stylesheet};
+
+impl Stylesheet {
+    /// Parses a stylesheet from its text form.
+    pub fn parse(source: &str) -> Result<Stylesheet, ParseError> {
+        let source = strip_comments(source);
+        let mut stylesheet = Stylesheet::new();
+        let mut position = 0;
+
+        loop {
+            let rest = &source[position..];
+            if rest.trim().is_empty() {
+                return Ok(stylesheet);
+            }
+
+            // Every rule is a list of selectors followed by a block of declarations
+            let selectors_start = position + (rest.len() - rest.trim_left().len());
+            let open = match rest.find('{') {
+                Some(v) => position + v,
+                None => return Err(error(&source, selectors_start, "expected '{' after selector"))
+            };
+            let close = match source[open..].find('}') {
+                Some(v) => open + v,
+                None => return Err(error(&source, open, "unclosed '{'"))
+            };
+
+            let mut selectors = Vec::new();
+            for selector in source[position..open].split(',') {
+                match Selector::parse(selector) {
+                    Ok(v) => selectors.push(v),
+                    Err(e) => return Err(error(&source, selectors_start, &e))
+                }
+            }
+
+            let style = match parse_declarations(&source[open + 1..close]) {
+                Ok(v) => v,
+                Err((offset, e)) => return Err(error(&source, open + 1 + offset, &e))
+            };
+
+            stylesheet.add_rule(Rule {
+                selectors: selectors,
+                style: style
+            });
+            position = close + 1;
+        }
+    }
+}
+
+/// Parses the declarations in a block, on failure returns the offset of the
+/// failing declaration together with the error.
+fn parse_declarations(block: &str) -> Result<Style, (usize, String)> {
+    let mut style = Style::new();
+    let mut offset = 0;
+
+    for declaration in block.split(';') {
+        // Errors point at the declaration itself, not the line break before it
+        let declaration_offset = offset + (declaration.len() - declaration.trim_left().len());
+        offset += declaration.len() + 1;
+
+        if declaration.trim().is_empty() {
+            continue;
+        }
+
+        let (name, value) = match declaration.find(':') {
+            Some(i) => (declaration[..i].trim(), declaration[i + 1..].trim()),
+            None => return Err((declaration_offset, format!("expected ':' in \"{}\"", declaration.trim())))
+        };
+
+        let result = match name {
+            "background" => parse_color(value).map(|v| style.background = Some(v)),
+            "image" => parse_string(value).map(|v| style.image = Some(v)),
+            "color" => parse_color(value).map(|v| style.color = Some(v)),
+            "font-size" => parse_length(value)
+                .and_then(|v| if v > 0 && v <= 255 { Ok(v as u8) } else { Err(format!("invalid font size \"{}\"", value)) })
+                .map(|v| style.font_size = Some(v)),
+            "padding" => parse_edges(value).map(|v| style.padding = Some(v)),
+            "margin" => parse_edges(value).map(|v| style.margin = Some(v)),
+            "border-width" => parse_length(value).map(|v| style.border_width = Some(v)),
+            "border-color" => parse_color(value).map(|v| style.border_color = Some(v)),
+            "border" => parse_border(value).map(|(width, color)| {
+                style.border_width = Some(width);
+                style.border_color = color;
+            }),
+            "width" => parse_length(value).map(|v| style.width = Some(v)),
+            "height" => parse_length(value).map(|v| style.height = Some(v)),
+            "min-width" => parse_length(value).map(|v| style.min_width = Some(v)),
+            "min-height" => parse_length(value).map(|v| style.min_height = Some(v)),
+            "max-width" => parse_length(value).map(|v| style.max_width = Some(v)),
+            "max-height" => parse_length(value).map(|v| style.max_height = Some(v)),
+            "corner-radius" => parse_length(value).map(|v| style.corner_radius = Some(v)),
+            "flex-grow" => parse_factor(value).map(|v| style.flex_grow = Some(v)),
+            "flex-shrink" => parse_factor(value).map(|v| style.flex_shrink = Some(v)),
+            "align-self" => Align::from_name(value)
+                .map(|v| style.align_self = Some(v))
+                .ok_or(format!("invalid alignment \"{}\"", value)),
+            _ => Err(format!("unknown property \"{}\"", name))
+        };
+
+        if let Err(e) = result {
+            return Err((declaration_offset, e));
+        }
+    }
+
+    Ok(style)
+}
+
+/// Parses a color in the `#rrggbb` format.
+pub fn parse_color(value: &str) -> Result<[f32; 3], String> {
+    if value.len() != 7 || !value.starts_with("#") || !value[1..].chars().all(|c| c.is_digit(16)) {
+        return Err(format!("invalid color \"{}\"", value));
+    }
+
+    let mut color = [0.0; 3];
+    for i in 0..3 {
+        let v = u8::from_str_radix(&value[1 + i*2..3 + i*2], 16).unwrap();
+        color[i] = (v as f32)/255.0;
+    }
+
+    Ok(color)
+}
+
+/// Parses a length in pixels that can't be negative, with an optional `px` suffix.
+pub fn parse_length(value: &str) -> Result<i32, String> {
+    let number = if value.ends_with("px") { &value[..value.len() - 2] } else { value };
+    match number.parse::<i32>() {
+        Ok(v) if v >= 0 => Ok(v),
+        _ => Err(format!("invalid length \"{}\"", value))
+    }
+}
+
+/// Parses a factor that can't be negative, such as `1` or `0.5`.
+fn parse_factor(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(v) if v >= 0.0 => Ok(v),
+        _ => Err(format!("invalid factor \"{}\"", value))
+    }
+}
+
+/// Parses a string surrounded by double quotes.
+fn parse_string(value: &str) -> Result<String, String> {
+    if value.len() < 2 || !value.starts_with("\"") || !value.ends_with("\"") {
+        return Err(format!("expected a quoted string, found \"{}\"", value));
+    }
+
+    Ok(String::from(&value[1..value.len() - 1]))
+}
+
+/// Parses a border width optionally followed by a color.
+fn parse_border(value: &str) -> Result<(i32, Option<[f32; 3]>), String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.len() {
+        1 => Ok((try!(parse_length(parts[0])), None)),
+        2 => Ok((try!(parse_length(parts[0])), Some(try!(parse_color(parts[1]))))),
+        _ => Err(format!("invalid border \"{}\"", value))
+    }
+}
+
+/// Parses one to four lengths in the order top, right, bottom, left.
+fn parse_edges(value: &str) -> Result<[i32; 4], String> {
+    let mut lengths = Vec::new();
+    for part in value.split_whitespace() {
+        lengths.push(try!(parse_length(part)));
+    }
+
+    match lengths.len() {
+        1 => Ok([lengths[0], lengths[0], lengths[0], lengths[0]]),
+        2 => Ok([lengths[0], lengths[1], lengths[0], lengths[1]]),
+        3 => Ok([lengths[0], lengths[1], lengths[2], lengths[1]]),
+        4 => Ok([lengths[0], lengths[1], lengths[2], lengths[3]]),
+        _ => Err(format!("expected one to four lengths, found \"{}\"", value))
+    }
+}
+
+/// Replaces comments with spaces, keeping line breaks so positions still match up.
+fn strip_comments(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find("*/").map(|i| start + i + 2).unwrap_or(rest.len());
+        for c in rest[start..end].chars() {
+            result.push(if c == '\n' { '\n' } else { ' ' });
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn error(source: &str, position: usize, message: &str) -> ParseError {
+    let line = source[..position].chars().filter(|&c| c == '\n').count() + 1;
+    ParseError::new(line, String::from(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use style::{Selector, Stylesheet};
+    use super::*;
+
+    #[test]
+    fn parse_reads_rules_in_order() {
+        let stylesheet = Stylesheet::parse(concat!(
+            "/* Buttons */\n",
+            "button, .action { background: #ff0000; padding: 1 2; }\n",
+            "#title { font-size: 18; border: 2 #00ff00; }")).unwrap();
+
+        let rules = stylesheet.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].selectors, vec![Selector::parse("button").unwrap(), Selector::parse(".action").unwrap()]);
+        assert_eq!(rules[0].style.background, Some([1.0, 0.0, 0.0]));
+        assert_eq!(rules[0].style.padding, Some([1, 2, 1, 2]));
+        assert_eq!(rules[1].style.font_size, Some(18));
+        assert_eq!(rules[1].style.border_width, Some(2));
+        assert_eq!(rules[1].style.border_color, Some([0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn parse_errors_carry_line_numbers() {
+        let line = |source: &str| Stylesheet::parse(source).unwrap_err().line;
+
+        assert_eq!(line("button { color: #ffffff; }\n\ntext { colour: #ffffff; }"), 3);
+        assert_eq!(line("button {\n    color: #ffffff;\n    width: wide;\n}"), 3);
+        assert_eq!(line("/* one\ntwo */\nbutton:wiggle { }"), 3);
+        assert_eq!(line("button { }\ntext\n"), 2);
+        assert_eq!(line("\nbutton { color: #ffffff;"), 2);
+    }
+
+    #[test]
+    fn parse_rejects_negative_lengths() {
+        assert_eq!(parse_length("12px"), Ok(12));
+        assert_eq!(parse_length("0"), Ok(0));
+        assert!(parse_length("-4").is_err());
+        assert!(parse_length("-4px").is_err());
+
+        for declaration in &["width: -1", "height: -1px", "padding: 2 -2", "border: -1 #000000", "border-width: -3"] {
+            assert!(Stylesheet::parse(&format!("button {{ {}; }}", declaration)).is_err(), "{}", declaration);
+        }
+    }
+
+    #[test]
+    fn parse_color_requires_six_digits() {
+        assert_eq!(parse_color("#ff8000"), Ok([1.0, 128.0/255.0, 0.0]));
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("red").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use widget::{self, Interaction};
+
+/// Selects widgets by element name, id, classes and interaction states, all of which have to match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector {
+    pub tag: Option<String>,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub states: Vec<Interaction>
+}
+
+impl Selector {
+    /// Parses a selector such as `button#quit.danger:hover`, or `*` to match anything.
+    pub fn parse(source: &str) -> Result<Selector, String> {
+        let mut selector = Selector::default();
+        let source = source.trim();
+
+        if source.is_empty() {
+            return Err(String::from("expected a selector"));
+        }
+        if source == "*" {
+            return Ok(selector);
+        }
+
+        let is_prefix = |c: char| c == '#' || c == '.' || c == ':';
+
+        let tag_end = source.find(&is_prefix).unwrap_or(source.len());
+        if tag_end != 0 && &source[..tag_end] != "*" {
+            selector.tag = Some(try!(parse_name(&source[..tag_end])));
+        }
+
+        let mut rest = &source[tag_end..];
+        while !rest.is_empty() {
+            let prefix = rest.chars().next().unwrap();
+            let end = rest[1..].find(&is_prefix).map(|i| i + 1).unwrap_or(rest.len());
+            let name = try!(parse_name(&rest[1..end]));
+
+            if prefix == '#' {
+                if selector.id.is_some() {
+                    return Err(format!("selector \"{}\" has more than one id", source));
+                }
+                selector.id = Some(name);
+            } else if prefix == ':' {
+                selector.states.push(try!(parse_state(&name)));
+            } else {
+                selector.classes.push(name);
+            }
+
+            rest = &rest[end..];
+        }
+
+        Ok(selector)
+    }
+
+    /// Gets the specificity of this selector as the amount of ids, classes and
+    /// element names in it. States count as classes.
+    pub fn specificity(&self) -> (usize, usize, usize) {
+        (
+            if self.id.is_some() { 1 } else { 0 },
+            self.classes.len() + self.states.len(),
+            if self.tag.is_some() { 1 } else { 0 }
+        )
+    }
+
+    /// Checks if this selector matches an element.
+    pub fn matches(&self, tag: &str, properties: &widget::Properties) -> bool {
+        if let Some(ref t) = self.tag {
+            if t != tag { return false; }
+        }
+
+        if let Some(ref id) = self.id {
+            if properties.id.as_ref() != Some(id) { return false; }
+        }
+
+        self.classes.iter().all(|c| properties.classes.contains(c)) &&
+            self.states.iter().all(|&s| properties.state.is(s))
+    }
+}
+
+fn parse_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("invalid name \"{}\" in selector", name));
+    }
+
+    Ok(String::from(name))
+}
+
+fn parse_state(name: &str) -> Result<Interaction, String> {
+    match name {
+        "hover" => Ok(Interaction::Hovered),
+        "pressed" => Ok(Interaction::Pressed),
+        "focus" => Ok(Interaction::Focused),
+        "disabled" => Ok(Interaction::Disabled),
+        "checked" => Ok(Interaction::Checked),
+        _ => Err(format!("unknown state \":{}\" in selector", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use widget::{Interaction, Properties};
+    use super::*;
+
+    #[test]
+    fn parse_reads_all_parts() {
+        let selector = Selector::parse("button#quit.danger.big:hover").unwrap();
+        assert_eq!(selector.tag, Some(String::from("button")));
+        assert_eq!(selector.id, Some(String::from("quit")));
+        assert_eq!(selector.classes, vec![String::from("danger"), String::from("big")]);
+        assert_eq!(selector.states, vec![Interaction::Hovered]);
+
+        assert_eq!(Selector::parse("*").unwrap(), Selector::default());
+        assert!(Selector::parse("#a#b").is_err());
+        assert!(Selector::parse("button:wiggle").is_err());
+        assert!(Selector::parse("").is_err());
+    }
+
+    #[test]
+    fn specificity_orders_ids_before_classes_before_tags() {
+        let id = Selector::parse("#quit").unwrap().specificity();
+        let classes = Selector::parse("button.a.b.c").unwrap().specificity();
+        let class = Selector::parse(".a").unwrap().specificity();
+        let state = Selector::parse(":hover").unwrap().specificity();
+        let tag = Selector::parse("button").unwrap().specificity();
+        let any = Selector::parse("*").unwrap().specificity();
+
+        assert!(id > classes);
+        assert!(classes > class);
+        assert_eq!(class, state);
+        assert!(class > tag);
+        assert!(tag > any);
+    }
+
+    #[test]
+    fn matches_requires_every_part() {
+        let mut properties = Properties::new();
+        properties.id = Some(String::from("quit"));
+        properties.classes.push(String::from("danger"));
+
+        assert!(Selector::parse("button").unwrap().matches("button", &properties));
+        assert!(!Selector::parse("text").unwrap().matches("button", &properties));
+        assert!(Selector::parse("button#quit.danger").unwrap().matches("button", &properties));
+        assert!(!Selector::parse("#quit.safe").unwrap().matches("button", &properties));
+        assert!(Selector::parse("*").unwrap().matches("text", &properties));
+    }
+
+    #[test]
+    fn matches_states() {
+        let mut properties = Properties::new();
+        let hover = Selector::parse("button:hover").unwrap();
+        let pressed = Selector::parse("button:hover:pressed").unwrap();
+        assert!(!hover.matches("button", &properties));
+
+        properties.state.hovered = true;
+        assert!(hover.matches("button", &properties));
+        assert!(!pressed.matches("button", &properties));
+
+        properties.state.pressed = true;
+        assert!(pressed.matches("button", &properties));
+    }
+}
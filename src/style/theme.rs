@@ -0,0 +1,270 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use style::{Rule, Selector, Style, Stylesheet};
+
+/// The colors used by a theme.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    /// Background of layouts.
+    pub background: [f32; 3],
+    /// Color of text shown on the background.
+    pub text: [f32; 3],
+    /// Color of text in disabled widgets.
+    pub text_disabled: [f32; 3],
+
+    /// Background of interactive widgets such as buttons.
+    pub surface: [f32; 3],
+    pub surface_hovered: [f32; 3],
+    pub surface_pressed: [f32; 3],
+    pub surface_disabled: [f32; 3],
+    /// Color of text shown on a surface.
+    pub on_surface: [f32; 3],
+    /// Color of the border around surfaces.
+    pub border: [f32; 3],
+
+    /// Background of checked widgets, and border of focused ones.
+    pub accent: [f32; 3]
+}
+
+/// A palette, font size, padding, border, corner radius and spacing scale
+/// giving all widgets a consistent look.
+///
+/// The spacing scale is applied through classes, `padding-small`,
+/// `padding-medium` and `padding-large` pad a widget on all sides, and
+/// `margin-small`, `margin-medium` and `margin-large` give it a margin.
+///
+/// ```
+/// gui.set_theme(phosphorus::style::Theme::light());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub palette: Palette,
+    pub font_size: u8,
+    /// Space between the edges of interactive widgets and their content, in
+    /// the order top, right, bottom, left.
+    pub padding: [i32; 4],
+    pub border_width: i32,
+    pub corner_radius: i32,
+    /// The small, medium and large amounts of space used by the spacing classes.
+    pub spacing: [i32; 3]
+}
+
+impl Theme {
+    /// Gets the dark theme, its buttons look the same as unthemed ones.
+    pub fn dark() -> Theme {
+        Theme {
+            palette: Palette {
+                background: rgb(21, 23, 24),
+                text: rgb(255, 255, 255),
+                text_disabled: rgb(128, 128, 128),
+
+                surface: rgb(71, 71, 71),
+                surface_hovered: rgb(87, 87, 87),
+                surface_pressed: rgb(56, 56, 56),
+                surface_disabled: rgb(51, 51, 51),
+                on_surface: rgb(255, 255, 255),
+                border: rgb(90, 90, 90),
+
+                accent: rgb(50, 110, 200)
+            },
+            font_size: 13,
+            padding: [1, 4, 1, 4],
+            border_width: 0,
+            corner_radius: 0,
+            spacing: [4, 8, 16]
+        }
+    }
+
+    /// Gets the light theme.
+    pub fn light() -> Theme {
+        Theme {
+            palette: Palette {
+                background: rgb(240, 240, 240),
+                text: rgb(30, 30, 30),
+                text_disabled: rgb(150, 150, 150),
+
+                surface: rgb(215, 215, 215),
+                surface_hovered: rgb(200, 200, 200),
+                surface_pressed: rgb(180, 180, 180),
+                surface_disabled: rgb(228, 228, 228),
+                on_surface: rgb(30, 30, 30),
+                border: rgb(170, 170, 170),
+
+                accent: rgb(90, 150, 235)
+            },
+            font_size: 13,
+            padding: [1, 4, 1, 4],
+            border_width: 0,
+            corner_radius: 3,
+            spacing: [4, 8, 16]
+        }
+    }
+
+    /// Gets a theme with black backgrounds, bright surfaces and larger text.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            palette: Palette {
+                background: rgb(0, 0, 0),
+                text: rgb(255, 255, 255),
+                text_disabled: rgb(160, 160, 160),
+
+                surface: rgb(255, 255, 0),
+                surface_hovered: rgb(255, 255, 160),
+                surface_pressed: rgb(200, 200, 0),
+                surface_disabled: rgb(80, 80, 80),
+                on_surface: rgb(0, 0, 0),
+                border: rgb(255, 255, 255),
+
+                accent: rgb(0, 255, 255)
+            },
+            font_size: 16,
+            padding: [2, 6, 2, 6],
+            border_width: 2,
+            corner_radius: 0,
+            spacing: [6, 12, 24]
+        }
+    }
+
+    /// Creates the stylesheet applying this theme to the built-in widgets.
+    pub fn to_stylesheet(&self) -> Stylesheet {
+        let palette = &self.palette;
+        let mut stylesheet = Stylesheet::new();
+
+        for tag in &["layout", "anchor-layout", "grid"] {
+            add_rule(&mut stylesheet, tag, Style {
+                background: Some(palette.background),
+                color: Some(palette.text),
+                font_size: Some(self.font_size),
+                .. Style::default()
+            });
+        }
+        // Widgets are also themed outside of a layout, so they don't rely on inheriting these
+        for tag in &["text", "image"] {
+            add_rule(&mut stylesheet, tag, Style {
+                color: Some(palette.text),
+                font_size: Some(self.font_size),
+                .. Style::default()
+            });
+        }
+        add_rule(&mut stylesheet, "button", Style {
+            background: Some(palette.surface),
+            color: Some(palette.on_surface),
+            font_size: Some(self.font_size),
+            padding: Some(self.padding),
+            border_width: Some(self.border_width),
+            border_color: Some(palette.border),
+            corner_radius: Some(self.corner_radius),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "button:hover", Style {
+            background: Some(palette.surface_hovered),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "button:focus", Style {
+            border_color: Some(palette.accent),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "button:checked", Style {
+            background: Some(palette.accent),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "button:pressed", Style {
+            background: Some(palette.surface_pressed),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "*:disabled", Style {
+            color: Some(palette.text_disabled),
+            .. Style::default()
+        });
+        add_rule(&mut stylesheet, "button:disabled", Style {
+            background: Some(palette.surface_disabled),
+            .. Style::default()
+        });
+
+        let sizes = ["small", "medium", "large"];
+        for (size, &space) in sizes.iter().zip(self.spacing.iter()) {
+            add_rule(&mut stylesheet, &format!(".padding-{}", size), Style {
+                padding: Some([space; 4]),
+                .. Style::default()
+            });
+            add_rule(&mut stylesheet, &format!(".margin-{}", size), Style {
+                margin: Some([space; 4]),
+                .. Style::default()
+            });
+        }
+
+        stylesheet
+    }
+}
+
+fn add_rule(stylesheet: &mut Stylesheet, selector: &str, style: Style) {
+    stylesheet.add_rule(Rule {
+        selectors: vec![Selector::parse(selector).unwrap()],
+        style: style
+    });
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> [f32; 3] {
+    [(r as f32)/255.0, (g as f32)/255.0, (b as f32)/255.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use widget::Properties;
+    use super::*;
+
+    const TAGS: [&'static str; 6] = ["layout", "anchor-layout", "grid", "button", "text", "image"];
+
+    fn themes() -> Vec<(&'static str, Theme)> {
+        vec![("dark", Theme::dark()), ("light", Theme::light()), ("high_contrast", Theme::high_contrast())]
+    }
+
+    #[test]
+    fn every_built_in_widget_has_a_foreground_color() {
+        for (name, theme) in themes() {
+            let stylesheet = theme.to_stylesheet();
+            let mut disabled = Properties::new();
+            disabled.state.disabled = true;
+
+            for tag in &TAGS {
+                let style = stylesheet.compute(tag, &Properties::new(), None);
+                assert!(style.color.is_some(), "{} has no color in the {} theme", tag, name);
+                assert!(style.font_size.is_some(), "{} has no font size in the {} theme", tag, name);
+
+                let style = stylesheet.compute(tag, &disabled, None);
+                assert_eq!(style.color, Some(theme.palette.text_disabled), "disabled {} in the {} theme", tag, name);
+            }
+        }
+    }
+
+    #[test]
+    fn spacing_classes_pad_and_space_widgets() {
+        for (_, theme) in themes() {
+            let stylesheet = theme.to_stylesheet();
+            let sizes = ["small", "medium", "large"];
+            for (size, &space) in sizes.iter().zip(theme.spacing.iter()) {
+                let mut properties = Properties::new();
+                properties.classes = vec![format!("padding-{}", size), format!("margin-{}", size)];
+
+                // The classes override the theme's padding of buttons
+                let style = stylesheet.compute("button", &properties, None);
+                assert_eq!(style.padding, Some([space; 4]));
+                assert_eq!(style.margin, Some([space; 4]));
+            }
+
+            assert!(theme.spacing[0] < theme.spacing[1] && theme.spacing[1] < theme.spacing[2]);
+        }
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use gfx;
+use focus;
+use geometry;
+use render;
+use style;
+use widget::{self, Widget};
+use {Error, Event, EventResult, Key, Modifiers, MouseButton};
+
+/// The widgets of a `Gui` and the input state tracked for them, kept apart
+/// from the device resources so it can be used without a window.
+pub struct Tree<R: gfx::Resources, M: render::TextMeasurer> {
+    root: widget::Layout<R>,
+    /// The theme's and the stylesheet's rules combined, applied when rendering.
+    cascade: style::Stylesheet,
+    /// The widgets each held down mouse button was pressed on, as the path of
+    /// child indices from the root leading to them, and if the press was consumed.
+    press_targets: HashMap<MouseButton, (Vec<usize>, bool)>,
+    /// The widget the mouse was last over, as the path of child indices from the root.
+    hover_path: Option<Vec<usize>>,
+    /// If the last mouse event with a position was consumed by a widget.
+    mouse_over_widget: bool,
+    /// The position of the mouse in units, if it's in the window.
+    mouse_position: Option<[i32; 2]>,
+    /// The modifier keys held down as of the last key event.
+    modifiers: Modifiers,
+    focus: focus::FocusManager,
+    /// The area the widgets were last laid out in, in units.
+    layout_area: Option<render::RenderArea>,
+    /// Measures the widgets' text when they're laid out.
+    measurer: Rc<RefCell<M>>
+}
+
+impl<R: gfx::Resources + 'static, M: render::TextMeasurer> Tree<R, M> {
+    /// Initializes a new `Tree` with nothing hovered, pressed or focused.
+    pub fn new(root: widget::Layout<R>, measurer: Rc<RefCell<M>>) -> Tree<R, M> {
+        Tree {
+            root: root,
+            cascade: style::Stylesheet::new(),
+            press_targets: HashMap::new(),
+            hover_path: None,
+            mouse_over_widget: false,
+            mouse_position: None,
+            modifiers: Modifiers::new(),
+            focus: focus::FocusManager::new(),
+            layout_area: None,
+            measurer: measurer
+        }
+    }
+
+    /// Gets the root layout as immutable.
+    pub fn root(&self) -> &widget::Layout<R> { &self.root }
+
+    /// Changes the root layout, then finds the hovered and focused widgets
+    /// again, see `find_paths_again`.
+    pub fn with_root<T, C: FnOnce(&mut widget::Layout<R>) -> T>(&mut self, change: C) -> T {
+        let result = change(&mut self.root);
+        self.find_paths_again();
+        result
+    }
+
+    /// Replaces the root layout. Nothing in the new tree is focused, and
+    /// buttons held down on the old tree don't click anything in it.
+    pub fn set_root(&mut self, root: widget::Layout<R>) {
+        self.root = root;
+        self.focus = focus::FocusManager::new();
+        self.find_paths_again();
+    }
+
+    /// Sets the rules the widgets are styled with, taking effect on the next update.
+    pub fn set_cascade(&mut self, cascade: style::Stylesheet) {
+        self.cascade = cascade;
+    }
+
+    /// Finds a widget in the tree by its id.
+    pub fn find_by_id(&self, id: &str) -> Option<&widget::Widget<R>> { self.root.find_by_id(id) }
+    /// Finds a widget in the tree by its id as mutable.
+    pub fn find_by_id_mut(&mut self, id: &str) -> Option<&mut widget::Widget<R>> { self.root.find_by_id_mut(id) }
+
+    /// Styles, measures and arranges the widgets to fill `area`, in units.
+    pub fn update(&mut self, area: &render::RenderArea) -> Result<(), Error> {
+        self.cascade.apply(&mut self.root, None);
+        self.update_layout(area)
+    }
+
+    /// Measures and arranges the widgets to fill `area`, only redoing the
+    /// parts of the tree that changed since the last layout.
+    fn update_layout(&mut self, area: &render::RenderArea) -> Result<(), Error> {
+        self.layout_area = Some(*area);
+
+        // Text is measured first so the widgets showing it fit it, if that
+        // fails the widgets are still laid out to estimates of their text
+        let result = self.root.measure_text(&mut *self.measurer.borrow_mut());
+
+        // The root layout fills the whole area, except for its margin
+        let margin = self.root.properties().computed_style.margin.unwrap_or([0; 4]);
+        let root_area = render::RenderArea {
+            position: [area.position[0] + margin[3], area.position[1] + margin[0]],
+            size: [area.size[0] - margin[1] - margin[3], area.size[1] - margin[0] - margin[2]]
+        };
+
+        self.root.measure(&geometry::Constraints::tight(root_area.size));
+        self.root.arrange(root_area);
+        result
+    }
+
+    /// Gets the widget that receives keyboard input, if there is one.
+    pub fn focused(&self) -> Option<&widget::Widget<R>> {
+        match self.focus.focused_path(&self.root) {
+            Some(path) => widget::descendant(&self.root, path),
+            None => None
+        }
+    }
+
+    /// Gives keyboard input to a widget found by its id. Returns if it was
+    /// focused, which it isn't if it can't be or wasn't found.
+    pub fn focus_by_id(&mut self, id: &str) -> bool {
+        let path = widget::find_paths(&self.root, &|w: &widget::Widget<R>| w.id() == Some(id)).into_iter().next();
+        self.focus.set_focus(&mut self.root, path)
+    }
+
+    /// Removes focus from the focused widget, if there is one.
+    pub fn clear_focus(&mut self) {
+        self.focus.set_focus(&mut self.root, None);
+    }
+
+    /// Checks if the mouse is over a widget that consumes mouse events, or
+    /// a mouse button is held down after being pressed on one. The
+    /// application shouldn't handle the mouse itself while this is true.
+    pub fn wants_mouse(&self) -> bool {
+        self.mouse_over_widget || self.press_targets.values().any(|&(_, consumed)| consumed)
+    }
+
+    /// Checks if a widget is focused and receives keyboard input.
+    pub fn wants_keyboard(&self) -> bool {
+        self.focused().is_some()
+    }
+
+    /// Raises an event with mouse positions in units, laying the widgets out
+    /// in `area` first, see `Gui::raise_event`.
+    pub fn raise_event(&mut self, event: Event, area: &render::RenderArea, app_state: &mut Any) -> EventResult {
+        // Failing to measure text is reported when rendering
+        let _ = self.update_layout(area);
+
+        match event {
+            Event::KeyDown(_, modifiers) | Event::KeyUp(_, modifiers) => self.modifiers = modifiers,
+            Event::MouseLeave => self.mouse_position = None,
+            _ => if let Some(position) = event.position() {
+                self.mouse_position = Some(position);
+            }
+        }
+
+        match event {
+            // Tab is taken for moving focus, also when it's typed as text
+            Event::KeyDown(Key::Tab, modifiers) if !(modifiers.control || modifiers.alt || modifiers.logo) => {
+                self.focus.focus_next(&mut self.root, modifiers.shift);
+                self.keyboard_result()
+            },
+            Event::KeyUp(Key::Tab, modifiers) if !(modifiers.control || modifiers.alt || modifiers.logo) =>
+                self.keyboard_result(),
+            Event::TextInput('\t') => self.keyboard_result(),
+            Event::KeyDown(..) | Event::KeyUp(..) | Event::TextInput(_) => {
+                let path = self.focus.focused_path(&self.root).map(|p| p.to_vec());
+                match path {
+                    Some(path) => self.dispatch(&path, &event, app_state),
+                    None => {
+                        // Forget a widget that can't be focused anymore
+                        self.clear_focus();
+                        EventResult::Ignored
+                    }
+                }
+            },
+            _ => self.raise_mouse_event(event, app_state)
+        }
+    }
+
+    /// Gets the result of a key event the Gui handles itself, which is
+    /// consumed if a widget receives keyboard input.
+    fn keyboard_result(&self) -> EventResult {
+        if self.wants_keyboard() { EventResult::Consumed } else { EventResult::Ignored }
+    }
+
+    /// Dispatches a mouse event to the widget under the mouse, then clicks and
+    /// focuses widgets as buttons are pressed and released on them. Entering
+    /// and leaving the window is dispatched to the root.
+    fn raise_mouse_event(&mut self, event: Event, app_state: &mut Any) -> EventResult {
+        match event {
+            Event::MouseLeave => self.hover_path = None,
+            _ => if let Some(position) = event.position() {
+                self.hover_path = self.root.widget_at(position);
+            }
+        }
+        self.update_mouse_states(&event);
+
+        let mut result = match event {
+            Event::MouseEnter | Event::MouseLeave => self.dispatch(&[], &event, app_state),
+            _ => match self.hover_path.clone() {
+                Some(path) => self.dispatch_under_mouse(&path, &event, app_state),
+                None => EventResult::Ignored
+            }
+        };
+        match event {
+            Event::MouseLeave => self.mouse_over_widget = false,
+            _ => if event.position().is_some() {
+                self.mouse_over_widget = result.is_consumed();
+            }
+        }
+
+        // Releasing a button over the widget it was pressed on clicks that widget
+        match event {
+            Event::MouseDown(button, _) => {
+                let target = self.hover_path.clone();
+                if button == MouseButton::Left {
+                    match target {
+                        Some(ref path) => self.focus.focus_at(&mut self.root, path),
+                        None => { self.focus.set_focus(&mut self.root, None); }
+                    }
+                }
+
+                match target {
+                    Some(path) => { self.press_targets.insert(button, (path, result.is_consumed())); },
+                    None => { self.press_targets.remove(&button); }
+                }
+            },
+            Event::MouseUp(button, position) => {
+                // A release ending a press on a widget is that widget's, wherever it is
+                if let Some((path, consumed)) = self.press_targets.remove(&button) {
+                    if consumed {
+                        result = EventResult::Consumed;
+                    }
+
+                    if Some(&path) == self.hover_path.as_ref() {
+                        let click = Event::MouseClick(button, position);
+                        result = self.dispatch_under_mouse(&path, &click, app_state).or(result);
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        result
+    }
+
+    /// Dispatches an event to the widget at a path, then makes the changes to
+    /// the tree its callbacks and handlers queued.
+    fn dispatch(&mut self, path: &[usize], event: &Event, app_state: &mut Any) -> EventResult {
+        let mut context = widget::EventContext::new(path, self.mouse_position, self.modifiers, app_state);
+        widget::dispatch(&mut self.root, event, &mut context);
+        if context.apply_changes(&mut self.root) {
+            self.find_paths_again();
+        }
+        context.result()
+    }
+
+    /// Finds the widgets the Gui tracks by their paths again after the tree
+    /// changed, as the paths may lead to other widgets now. Buttons held down
+    /// before the change don't click anything when they're released.
+    fn find_paths_again(&mut self) {
+        self.press_targets.clear();
+        self.focus.find_focused(&mut self.root);
+
+        // Lay out the changed tree to find the widget under the mouse in it
+        if let Some(area) = self.layout_area {
+            self.cascade.apply(&mut self.root, None);
+            let _ = self.update_layout(&area);
+        }
+        self.hover_path = match self.mouse_position {
+            Some(position) => self.root.widget_at(position),
+            None => None
+        };
+
+        let path = self.hover_path.as_ref().map(|p| &p[..]);
+        widget::update_states(&mut self.root, path, &|state: &mut widget::State, on: bool| state.hovered = on);
+    }
+
+    /// Dispatches a mouse event to the widget at a path, which is consumed if
+    /// a widget handled it or any widget on the path below the root is opaque.
+    fn dispatch_under_mouse(&mut self, path: &[usize], event: &Event, app_state: &mut Any) -> EventResult {
+        let result = self.dispatch(path, event, app_state);
+        if is_covered(&self.root, path) { EventResult::Consumed } else { result }
+    }
+
+    /// Updates the hovered and pressed states of the widgets for a mouse
+    /// event. Only the widget under the mouse and its ancestors are hovered,
+    /// and the left mouse button presses them unless they're disabled.
+    fn update_mouse_states(&mut self, event: &Event) {
+        if event.position().is_none() && *event != Event::MouseLeave {
+            return;
+        }
+
+        let path = self.hover_path.as_ref().map(|p| &p[..]);
+        widget::update_states(&mut self.root, path, &|state: &mut widget::State, on: bool| state.hovered = on);
+
+        match *event {
+            Event::MouseDown(MouseButton::Left, _) =>
+                widget::update_states(&mut self.root, path, &|state: &mut widget::State, on: bool| {
+                    state.pressed = on && !state.disabled;
+                }),
+            Event::MouseUp(MouseButton::Left, _) =>
+                widget::update_states(&mut self.root, None, &|state: &mut widget::State, _| state.pressed = false),
+            _ => {}
+        }
+    }
+}
+
+/// Checks if any widget on a path from the root is opaque, keeping the mouse
+/// from reaching what the application renders behind the Gui. The root fills
+/// the whole window, so it doesn't count.
+pub fn is_covered<R: gfx::Resources>(root: &Widget<R>, path: &[usize]) -> bool {
+    (1..path.len() + 1).any(|length| {
+        widget::descendant(root, &path[..length]).map(|w| w.is_opaque()).unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use gfx_device_gl::Resources;
+    use render::{RenderArea, TextMeasurer};
+    use widget::{ButtonBuilder, EventContext, Layout, LayoutBuilder, Widget};
+    use {Error, Event, EventResult, Key, Modifiers, MouseButton};
+    use super::*;
+
+    /// Measures every character as 7 wide.
+    struct FixedMeasurer;
+
+    impl TextMeasurer for FixedMeasurer {
+        fn text_width(&mut self, text: &str, _size: u8) -> Result<i32, Error> {
+            Ok(7*text.chars().count() as i32)
+        }
+    }
+
+    /// The ids of the buttons clicked, in order.
+    type Clicks = Vec<&'static str>;
+
+    const AREA: RenderArea = RenderArea { position: [0, 0], size: [200, 200] };
+
+    fn button(id: &'static str, disabled: bool) -> Box<Widget<Resources>> {
+        ButtonBuilder::new()
+            .with_id(id)
+            .with_disabled(disabled)
+            .with_callback(Box::new(move |context: &mut EventContext| {
+                context.app_state::<Clicks>().unwrap().push(id);
+            }))
+            .build_boxed()
+    }
+
+    /// Builds a column of buttons that record their clicks.
+    fn buttons(ids: &[&'static str]) -> Layout<Resources> {
+        let mut builder = LayoutBuilder::new();
+        for id in ids {
+            builder = builder.with_widget(button(id, false));
+        }
+        builder.build()
+    }
+
+    fn tree(root: Layout<Resources>) -> Tree<Resources, FixedMeasurer> {
+        let mut tree = Tree::new(root, Rc::new(RefCell::new(FixedMeasurer)));
+        tree.update(&AREA).unwrap();
+        tree
+    }
+
+    /// Gets a point in the middle of a widget found by its id.
+    fn point_in(tree: &Tree<Resources, FixedMeasurer>, id: &str) -> [i32; 2] {
+        let area = tree.find_by_id(id).unwrap().area();
+        [area.position[0] + area.size[0]/2, area.position[1] + area.size[1]/2]
+    }
+
+    fn raise(tree: &mut Tree<Resources, FixedMeasurer>, event: Event, clicks: &mut Clicks) -> EventResult {
+        tree.raise_event(event, &AREA, clicks)
+    }
+
+    fn focused_id(tree: &Tree<Resources, FixedMeasurer>) -> Option<String> {
+        tree.focused().and_then(|w| w.id()).map(String::from)
+    }
+
+    #[test]
+    fn pressing_and_releasing_on_a_widget_clicks_it() {
+        let mut tree = tree(buttons(&["a", "b"]));
+        let mut clicks = Clicks::new();
+        let a = point_in(&tree, "a");
+
+        assert_eq!(raise(&mut tree, Event::MouseDown(MouseButton::Left, a), &mut clicks), EventResult::Consumed);
+        assert!(tree.find_by_id("a").unwrap().state().pressed);
+        assert!(clicks.is_empty());
+
+        assert_eq!(raise(&mut tree, Event::MouseUp(MouseButton::Left, a), &mut clicks), EventResult::Consumed);
+        assert!(!tree.find_by_id("a").unwrap().state().pressed);
+        assert_eq!(clicks, vec!["a"]);
+        assert_eq!(focused_id(&tree), Some(String::from("a")));
+
+        // Buttons only react to the left mouse button
+        raise(&mut tree, Event::MouseDown(MouseButton::Right, a), &mut clicks);
+        raise(&mut tree, Event::MouseUp(MouseButton::Right, a), &mut clicks);
+        assert_eq!(clicks, vec!["a"]);
+    }
+
+    #[test]
+    fn releasing_elsewhere_does_not_click() {
+        let mut tree = tree(buttons(&["a", "b"]));
+        let mut clicks = Clicks::new();
+        let (a, b, empty) = (point_in(&tree, "a"), point_in(&tree, "b"), [199, 199]);
+
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, a), &mut clicks);
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, b), &mut clicks);
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, a), &mut clicks);
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, empty), &mut clicks);
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, empty), &mut clicks);
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, a), &mut clicks);
+        assert!(clicks.is_empty());
+
+        // Leaving the widget while the button is held down doesn't cancel the click
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, b), &mut clicks);
+        raise(&mut tree, Event::MouseMoved(a), &mut clicks);
+        raise(&mut tree, Event::MouseMoved(b), &mut clicks);
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, b), &mut clicks);
+        assert_eq!(clicks, vec!["b"]);
+    }
+
+    #[test]
+    fn tab_moves_focus_past_disabled_widgets() {
+        let root = LayoutBuilder::new()
+            .with_widget(button("a", false))
+            .with_widget(button("b", true))
+            .with_widget(button("c", false))
+            .build();
+        let mut tree = tree(root);
+        let mut clicks = Clicks::new();
+        let tab = Modifiers::new();
+        let shift_tab = Modifiers { shift: true, .. Modifiers::new() };
+
+        let expected_forwards = ["a", "c", "a"];
+        for id in &expected_forwards {
+            assert_eq!(raise(&mut tree, Event::KeyDown(Key::Tab, tab), &mut clicks), EventResult::Consumed);
+            assert_eq!(focused_id(&tree), Some(String::from(*id)));
+        }
+
+        let expected_backwards = ["c", "a", "c"];
+        for id in &expected_backwards {
+            raise(&mut tree, Event::KeyDown(Key::Tab, shift_tab), &mut clicks);
+            assert_eq!(focused_id(&tree), Some(String::from(*id)));
+        }
+
+        // The focused widget receives keys
+        raise(&mut tree, Event::KeyDown(Key::Enter, tab), &mut clicks);
+        assert_eq!(clicks, vec!["c"]);
+    }
+
+    #[test]
+    fn replacing_the_root_forgets_presses_and_focus() {
+        let mut tree = tree(buttons(&["a", "b"]));
+        let mut clicks = Clicks::new();
+        let a = point_in(&tree, "a");
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, a), &mut clicks);
+        assert!(tree.wants_keyboard());
+
+        // The new tree is hovered where the mouse is, but nothing in it is pressed
+        tree.set_root(buttons(&["a", "b"]));
+        assert!(tree.find_by_id("a").unwrap().state().hovered);
+        assert!(!tree.wants_keyboard());
+
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, a), &mut clicks);
+        assert!(clicks.is_empty());
+    }
+
+    #[test]
+    fn changing_the_root_forgets_presses_and_keeps_focus() {
+        let mut tree = tree(buttons(&["a", "b"]));
+        let mut clicks = Clicks::new();
+        let a = point_in(&tree, "a");
+        raise(&mut tree, Event::MouseDown(MouseButton::Left, a), &mut clicks);
+
+        // Another button takes the place of the pressed one
+        tree.with_root(|root| root.insert(0, button("c", false)));
+        assert_eq!(focused_id(&tree), Some(String::from("a")));
+        assert_eq!(point_in(&tree, "c"), a);
+
+        raise(&mut tree, Event::MouseUp(MouseButton::Left, a), &mut clicks);
+        assert!(clicks.is_empty());
+    }
+}
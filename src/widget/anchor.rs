@@ -0,0 +1,205 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use std::any::Any;
+use gfx;
+use geometry::{self, Anchor, Anchors};
+use markup;
+use widget;
+use render;
+use Error;
+
+/// Object that allows you to build anchor layout widgets.
+pub struct AnchorLayoutBuilder<R: gfx::Resources> {
+    properties: widget::Properties,
+    widgets: Vec<(Box<widget::Widget<R>>, Anchors)>
+}
+
+impl<R: gfx::Resources> AnchorLayoutBuilder<R> {
+    /// Initializes a new `AnchorLayoutBuilder` with default values.
+    pub fn new() -> AnchorLayoutBuilder<R> {
+        AnchorLayoutBuilder {
+            properties: widget::Properties::new(),
+            widgets: Vec::new()
+        }
+    }
+
+    builder_properties!();
+
+    /// Requests a child widget to be pinned in the layout by anchors.
+    pub fn with_widget(mut self, widget: Box<widget::Widget<R>>, anchors: Anchors) -> AnchorLayoutBuilder<R> {
+        self.widgets.push((widget, anchors));
+        self
+    }
+
+    pub fn build(self) -> AnchorLayout<R> {
+        AnchorLayout {
+            properties: self.properties,
+            widgets: self.widgets
+        }
+    }
+}
+
+/// Represents a widget pinning other widgets to its edges or center, such as
+/// the elements of a game's HUD.
+///
+/// Unless its style gives it a size, the layout fills all the space it's
+/// given, so it can cover the whole screen.
+pub struct AnchorLayout<R: gfx::Resources> {
+    properties: widget::Properties,
+    widgets: Vec<(Box<widget::Widget<R>>, Anchors)>
+}
+
+impl<R: gfx::Resources> AnchorLayout<R> {
+    /// Gets the amount of child widgets.
+    pub fn len(&self) -> usize { self.widgets.len() }
+    /// Checks if the layout has no child widgets.
+    pub fn is_empty(&self) -> bool { self.widgets.is_empty() }
+
+    /// Adds a child widget pinned by anchors, in front of all existing ones.
+    pub fn push(&mut self, widget: Box<widget::Widget<R>>, anchors: Anchors) {
+        self.widgets.push((widget, anchors));
+        self.properties.layout.invalidate();
+    }
+
+    /// Removes and returns the child widget at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<widget::Widget<R>> {
+        self.properties.layout.invalidate();
+        self.widgets.remove(index).0
+    }
+
+    /// Removes all child widgets.
+    pub fn clear(&mut self) {
+        self.widgets.clear();
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the anchors pinning the child widget at `index`.
+    pub fn anchors(&self, index: usize) -> Option<&Anchors> {
+        self.widgets.get(index).map(|w| &w.1)
+    }
+
+    /// Pins the child widget at `index` by other anchors.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_anchors(&mut self, index: usize, anchors: Anchors) {
+        self.widgets[index].1 = anchors;
+        self.properties.layout.invalidate();
+    }
+
+    /// Describes this layout and all of its children as a markup element.
+    pub fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("anchor-layout");
+        element.children = self.widgets.iter().map(|&(ref widget, ref anchors)| {
+            let mut child = widget.to_element();
+            anchors_to_attributes(anchors, &mut child);
+            child
+        }).collect();
+        element
+    }
+
+    /// Writes this layout and all of its children as a markup document.
+    pub fn to_markup(&self) -> String {
+        self.to_element().to_markup()
+    }
+}
+
+impl<R: gfx::Resources + 'static> widget::Widget<R> for AnchorLayout<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "anchor-layout" }
+
+    /// Fills the space the constraints allow, on axes without a limit the
+    /// layout takes the size needed to fit all children with their offsets.
+    fn compute_size(&mut self, constraints: &geometry::Constraints) -> [i32; 2] {
+        let style = &self.properties.computed_style;
+        let insets = style.insets([0; 4]);
+        let insets_size = [insets[1] + insets[3], insets[0] + insets[2]];
+
+        let mut available = geometry::Constraints::loose(constraints.shrink(insets_size).max);
+        if let Some(width) = style.width { available.max[0] = std::cmp::max(width - insets_size[0], 0); }
+        if let Some(height) = style.height { available.max[1] = std::cmp::max(height - insets_size[1], 0); }
+
+        let mut content = [0, 0];
+        for &mut (ref mut widget, ref anchors) in &mut self.widgets {
+            let extent = anchors.extent(widget::measure_child(&mut **widget, &available));
+            content = [std::cmp::max(content[0], extent[0]), std::cmp::max(content[1], extent[1])];
+        }
+
+        let mut size = [content[0] + insets_size[0], content[1] + insets_size[1]];
+        for axis in 0..2 {
+            if constraints.is_bounded(axis) {
+                size[axis] = std::cmp::max(size[axis], constraints.max[axis]);
+            }
+        }
+        style.size_or(size)
+    }
+
+    fn arrange_children(&mut self, area: &render::RenderArea) {
+        let content = self.properties.computed_style.content_area(area.position, area.size, [0; 4]);
+        for &mut (ref mut widget, ref anchors) in &mut self.widgets {
+            let child_area = anchors.arrange(&content, widget::measured_outer_size(&**widget));
+            widget::arrange_child(&mut **widget, &child_area);
+        }
+    }
+
+    fn to_element(&self) -> markup::Element { AnchorLayout::to_element(self) }
+
+    fn child_count(&self) -> usize { self.widgets.len() }
+    fn child(&self, index: usize) -> Option<&widget::Widget<R>> { self.widgets.get(index).map(|w| &*w.0) }
+    fn child_mut(&mut self, index: usize) -> Option<&mut widget::Widget<R>> {
+        match self.widgets.get_mut(index) {
+            Some(w) => Some(&mut *w.0),
+            None => None
+        }
+    }
+
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        let area = self.properties.layout.area();
+        try!(renderer.render_box(area.position, area.size, &self.properties.computed_style, None, None));
+
+        for &(ref widget, _) in &self.widgets {
+            try!(widget.render(renderer));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the anchors pinning a child widget as attributes of its element.
+fn anchors_to_attributes(anchors: &Anchors, element: &mut markup::Element) {
+    let axes = [
+        (anchors.horizontal, "left", "right", "center-x"),
+        (anchors.vertical, "top", "bottom", "center-y")];
+
+    for &(anchor, start, end, center) in axes.iter() {
+        let mut set = |name: &str, value: i32| { element.attributes.insert(String::from(name), value.to_string()); };
+        match anchor {
+            Anchor::Start(offset) => set(start, offset),
+            Anchor::End(offset) => set(end, offset),
+            Anchor::Center(offset) => set(center, offset),
+            Anchor::Stretch(from_start, from_end) => {
+                set(start, from_start);
+                set(end, from_end);
+            }
+        }
+    }
+}
@@ -13,28 +13,41 @@
 // limitations under the License.
 
 use std;
+use std::any::Any;
 use gfx;
+use geometry;
+use markup;
 use widget;
 use render;
+use Error;
 use Event;
+use Key;
+use MouseButton;
+
+/// The padding of buttons without one set by their style.
+const DEFAULT_PADDING: [i32; 4] = [1, 4, 1, 4];
 
 /// Object that allows you to build button widgets.
 pub struct ButtonBuilder {
+    properties: widget::Properties,
     text: String,
     size: [i32; 2],
-    callback: Option<Box<Fn()>>
+    callback: Option<Box<FnMut(&mut widget::EventContext)>>
 }
 
 impl ButtonBuilder {
     /// Initializes a new `ButtonBuilder` with default values.
     pub fn new() -> ButtonBuilder {
         ButtonBuilder {
+            properties: widget::Properties::new(),
             text: String::default(),
             size: [80, 20],
             callback: None
         }
     }
 
+    builder_properties!();
+
     /// Requests a text content for the widget.
     pub fn with_text(mut self, text: &str) -> ButtonBuilder {
         self.text = String::from(text);
@@ -46,8 +59,8 @@ impl ButtonBuilder {
         self
     }
 
-    // Requests a callback to be called on click.
-    pub fn with_callback(mut self, callback: Box<Fn()>) -> ButtonBuilder {
+    /// Requests a callback to be called on click, with the context of the event.
+    pub fn with_callback(mut self, callback: Box<FnMut(&mut widget::EventContext)>) -> ButtonBuilder {
         self.callback = Some(callback);
         self
     }
@@ -55,12 +68,11 @@ impl ButtonBuilder {
     /// Builds the widget.
     pub fn build_boxed<R: gfx::Resources>(self) -> Box<Button<R>> {
         Box::new(Button {
+            properties: self.properties,
             text: self.text,
             size: self.size,
             callback: self.callback,
 
-            hovering: false,
-
             _r: std::marker::PhantomData
         })
     }
@@ -68,56 +80,97 @@ impl ButtonBuilder {
 
 /// Represents a widget that detects mouse click input.
 pub struct Button<R: gfx::Resources> {
+    properties: widget::Properties,
     text: String,
     size: [i32; 2],
-    callback: Option<Box<Fn()>>,
-
-    hovering: bool,
+    callback: Option<Box<FnMut(&mut widget::EventContext)>>,
 
     _r: std::marker::PhantomData<R>
 }
 
-impl<R: gfx::Resources> widget::Widget<R> for Button<R> {
-    fn raise_event(
-        &mut self, event: &Event,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset)
-    {
-        match event {
-            &Event::MouseMoved(mouse_pos) => {
-                let pos = [
-                    (prev_area.position[0] + offset.position[0]),
-                    (prev_area.position[1] + offset.position[1])];
-
-                self.hovering =
-                    mouse_pos[0] > pos[0] && mouse_pos[1] > pos[1] &&
-                    mouse_pos[0] <= pos[0] + self.size[0] && mouse_pos[1] <= pos[1] + self.size[1];
-            },
-            &Event::MouseClick => {
-                if self.hovering {
-                    if let &Some(ref c) = &self.callback {
-                        c();
-                    }
-                }
-            },
-            _ => {}
-        }
+impl<R: gfx::Resources> Button<R> {
+    /// Gets the text shown on the button.
+    pub fn text(&self) -> &str { &self.text }
+    /// Sets the text shown on the button.
+    pub fn set_text(&mut self, text: &str) { self.text = String::from(text); }
+
+    /// Gets the size of the button.
+    pub fn size(&self) -> [i32; 2] { self.size }
+    /// Sets the size of the button.
+    pub fn set_size(&mut self, size: [i32; 2]) {
+        self.size = size;
+        self.properties.layout.invalidate();
+    }
+
+    /// Sets the callback to be called on click, replacing the previous one.
+    pub fn set_callback(&mut self, callback: Box<FnMut(&mut widget::EventContext)>) { self.callback = Some(callback); }
+}
 
-        // Increment the rendering offset for the next widget
-        offset.position[1] += self.size[1];
+impl<R: gfx::Resources + 'static> widget::Widget<R> for Button<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "button" }
+
+    fn is_focusable(&self) -> bool { !self.properties.state.disabled }
+
+    // Disabled buttons still keep the mouse from reaching what's behind them
+    fn is_opaque(&self) -> bool { true }
+
+    fn compute_size(&mut self, _constraints: &geometry::Constraints) -> [i32; 2] {
+        self.properties.computed_style.size_or(self.size)
     }
 
-    fn render(
-        &self, renderer: &mut render::Renderer<R>,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset)
-    {
-        let pos = [
-            (prev_area.position[0] + offset.position[0]),
-            (prev_area.position[1] + offset.position[1])];
+    fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("button");
+        element.text = Some(self.text.clone());
+        element.attributes.insert(String::from("width"), self.size[0].to_string());
+        element.attributes.insert(String::from("height"), self.size[1].to_string());
+        element
+    }
+
+    fn handle_event(&mut self, event: &Event, context: &mut widget::EventContext) {
+        if context.phase() != widget::Phase::Target {
+            return;
+        }
 
-        renderer.render_rect_flat(pos, self.size, if self.hovering {[0.34, 0.34, 0.34]} else {[0.28, 0.28, 0.28]});
-        renderer.render_text([pos[0] + 4, pos[1] + 1], &self.text);
+        // Other mouse buttons don't count, and keys only reach the button when it's focused
+        let activated = match event {
+            &Event::MouseClick(MouseButton::Left, _) => true,
+            &Event::KeyDown(Key::Enter, _) | &Event::KeyDown(Key::Space, _) => true,
+            _ => false
+        };
+
+        if activated && !self.properties.state.disabled {
+            if let Some(ref mut c) = self.callback {
+                c(context);
+            }
+            context.consume();
+        }
+    }
 
-        // Increment the rendering offset for the next widget
-        offset.position[1] += self.size[1];
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        let style = &self.properties.computed_style;
+        let area = self.properties.layout.area();
+        let (pos, size) = (area.position, area.size);
+
+        // Without a style the button's state still shows in its colors
+        let state = &self.properties.state;
+        let (default_background, default_color) =
+            if state.disabled { ([0.2, 0.2, 0.2], [0.5, 0.5, 0.5]) }
+            else if state.pressed { ([0.22, 0.22, 0.22], render::DEFAULT_TEXT_COLOR) }
+            else if state.hovered { ([0.34, 0.34, 0.34], render::DEFAULT_TEXT_COLOR) }
+            else { ([0.28, 0.28, 0.28], render::DEFAULT_TEXT_COLOR) };
+
+        try!(renderer.render_box(pos, size, style, Some(default_background), None));
+
+        let content = style.content_area(pos, size, DEFAULT_PADDING);
+        renderer.render_text(
+            content.position, &self.text,
+            style.color.unwrap_or(default_color),
+            style.font_size.unwrap_or(render::DEFAULT_FONT_SIZE))
     }
 }
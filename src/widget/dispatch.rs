@@ -0,0 +1,290 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use std::any::{Any, TypeId};
+use gfx;
+use widget::{self, Widget};
+use Event;
+use EventResult;
+use Error;
+use Modifiers;
+
+/// The part of dispatching an event a widget receives it in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// The event travels from the root down to the target, reaching its ancestors.
+    Capture,
+    /// The event reached the widget it's aimed at.
+    Target,
+    /// The event travels from the target back up to the root, reaching its ancestors.
+    Bubble
+}
+
+/// A function reacting to events reaching a widget, with the widget's
+/// properties and the context of the event's dispatch.
+pub type EventHandler = Box<FnMut(&mut widget::Properties, &Event, &mut EventContext)>;
+
+/// A change to a widget tree queued while an event is dispatched through it.
+type TreeChange<R> = Box<FnMut(&mut widget::Layout<R>)>;
+
+/// Describes where an event is in its dispatch through the widget tree, and
+/// lets the widgets it reaches stop it or mark it as handled.
+///
+/// The context also carries the input state when the event was raised and a
+/// mutable handle to the application's state, and it collects changes to the
+/// widget tree that can't be made while the event travels through it.
+pub struct EventContext<'a> {
+    phase: Phase,
+    target: Vec<usize>,
+    current: Vec<usize>,
+    id: Option<String>,
+    position: Option<[i32; 2]>,
+    modifiers: Modifiers,
+    app_state: &'a mut Any,
+    /// The type of the root of the tree the event is dispatched through, once it is.
+    tree: Option<TypeId>,
+    /// Queued changes, each a boxed `TreeChange` for the resources of the tree.
+    changes: Vec<Box<Any>>,
+    stopped: bool,
+    consumed: bool
+}
+
+impl<'a> EventContext<'a> {
+    /// Initializes a new `EventContext` for an event aimed at the widget at a
+    /// path, raised with the mouse at a position and modifier keys held down.
+    pub fn new(
+        target: &[usize], position: Option<[i32; 2]>, modifiers: Modifiers, app_state: &'a mut Any
+    ) -> EventContext<'a> {
+        EventContext {
+            phase: Phase::Capture,
+            target: target.to_vec(),
+            current: Vec::new(),
+            id: None,
+            position: position,
+            modifiers: modifiers,
+            app_state: app_state,
+            tree: None,
+            changes: Vec::new(),
+            stopped: false,
+            consumed: false
+        }
+    }
+
+    /// Gets the phase the event is currently in.
+    pub fn phase(&self) -> Phase { self.phase }
+
+    /// Gets the path of child indices from the root to the widget the event is aimed at.
+    pub fn target(&self) -> &[usize] { &self.target }
+    /// Gets the path of child indices from the root to the widget currently receiving the event.
+    pub fn current(&self) -> &[usize] { &self.current }
+    /// Gets the id of the widget currently receiving the event, if it has one.
+    pub fn id(&self) -> Option<&str> { self.id.as_ref().map(|v| &v[..]) }
+
+    /// Gets the position of the mouse when the event was raised, in the units
+    /// widgets are laid out in. This is `None` if the mouse isn't in the window.
+    pub fn position(&self) -> Option<[i32; 2]> { self.position }
+    /// Gets the modifier keys held down when the event was raised.
+    pub fn modifiers(&self) -> Modifiers { self.modifiers }
+
+    /// Gets the state the application raised the event with, if it's of type `A`.
+    ///
+    /// This is `None` if the state is of another type, including when the
+    /// event was raised without one, which `Gui::raise_event` does by passing
+    /// `()`. Callbacks that need the state should raise their events with
+    /// `Gui::raise_event_with`.
+    pub fn app_state<A: Any>(&mut self) -> Option<&mut A> {
+        self.app_state.downcast_mut::<A>()
+    }
+
+    /// Queues a change to the widget tree, made to its root once the event
+    /// has been dispatched. Widgets can be added, removed and changed this
+    /// way without invalidating the event's path through the tree.
+    ///
+    /// Returns `Error::TreeMismatch`, without queueing the change, if the
+    /// event is dispatched through a tree of other resources than `R`.
+    pub fn queue_change<R, C>(&mut self, change: C) -> Result<(), Error>
+        where R: gfx::Resources + 'static, C: FnOnce(&mut widget::Layout<R>) + 'static
+    {
+        if let Some(tree) = self.tree {
+            if tree != TypeId::of::<widget::Layout<R>>() {
+                return Err(Error::TreeMismatch);
+            }
+        }
+
+        let mut change = Some(change);
+        let change: TreeChange<R> = Box::new(move |root| if let Some(change) = change.take() { change(root) });
+        self.changes.push(Box::new(change));
+        Ok(())
+    }
+
+    /// Makes all queued changes to the root of a widget tree, in the order
+    /// they were queued. Returns if any changes were made.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a change was queued for a tree of other resources than `R`,
+    /// which can only happen if it was queued before the event was dispatched.
+    pub fn apply_changes<R: gfx::Resources + 'static>(&mut self, root: &mut widget::Layout<R>) -> bool {
+        let changed = !self.changes.is_empty();
+        for change in self.changes.drain(..) {
+            match change.downcast::<TreeChange<R>>() {
+                Ok(mut change) => (**change)(root),
+                Err(_) => panic!("change queued for a tree of other resources than it's applied to")
+            }
+        }
+
+        changed
+    }
+
+    /// Keeps the event from reaching any widget after the current one. Other
+    /// handlers of the current widget still receive it.
+    pub fn stop_propagation(&mut self) { self.stopped = true; }
+    /// Checks if a widget stopped the event from reaching further widgets.
+    pub fn is_propagation_stopped(&self) -> bool { self.stopped }
+
+    /// Marks the event as handled, so the application doesn't handle it too.
+    pub fn consume(&mut self) { self.consumed = true; }
+    /// Checks if a widget marked the event as handled.
+    pub fn is_consumed(&self) -> bool { self.consumed }
+
+    /// Gets the result of the dispatch for the application.
+    pub fn result(&self) -> EventResult {
+        if self.consumed { EventResult::Consumed } else { EventResult::Ignored }
+    }
+}
+
+/// Dispatches an event from `root` to the widget at the context's target
+/// path. The event is first captured by the target's ancestors from the root
+/// down, then reaches the target, and then bubbles back up through the
+/// ancestors, until a widget stops its propagation.
+///
+/// Every widget handles the event itself before its handlers are called.
+pub fn dispatch<R: gfx::Resources + 'static>(root: &mut Widget<R>, event: &Event, context: &mut EventContext) {
+    let target = context.target.clone();
+    context.tree = Some(TypeId::of::<widget::Layout<R>>());
+
+    for length in 0..target.len() {
+        visit(root, &target[..length], Phase::Capture, event, context);
+        if context.stopped {
+            return;
+        }
+    }
+
+    visit(root, &target, Phase::Target, event, context);
+
+    for length in (0..target.len()).rev() {
+        if context.stopped {
+            break;
+        }
+        visit(root, &target[..length], Phase::Bubble, event, context);
+    }
+}
+
+/// Lets the widget at a path handle an event in a phase.
+fn visit<R: gfx::Resources>(root: &mut Widget<R>, path: &[usize], phase: Phase, event: &Event, context: &mut EventContext) {
+    let widget = match widget::descendant_mut(root, path) {
+        Some(widget) => widget,
+        None => return
+    };
+
+    context.phase = phase;
+    context.current = path.to_vec();
+    context.id = widget.id().map(String::from);
+    widget.handle_event(event, context);
+
+    // The handlers are moved out while they run, so they can change the widget's properties
+    let mut handlers = std::mem::replace(&mut widget.properties_mut().handlers, Vec::new());
+    for entry in &mut handlers {
+        // The target receives the event regardless of the phase handlers were added for
+        if entry.0 == phase || phase == Phase::Target {
+            (*entry.1)(widget.properties_mut(), event, context);
+        }
+    }
+
+    // Keep the handlers that were added while these ran after them
+    let added = std::mem::replace(&mut widget.properties_mut().handlers, handlers);
+    widget.properties_mut().handlers.extend(added);
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use widget::{Layout, LayoutBuilder, TextBuilder};
+    use Event;
+    use Modifiers;
+    use super::*;
+
+    fn text(id: &str) -> Box<widget::Text<Resources>> {
+        TextBuilder::new().with_id(id).build_boxed()
+    }
+
+    fn ids(root: &Layout<Resources>) -> Vec<String> {
+        (0..root.len()).map(|i| String::from(root.child(i).unwrap().id().unwrap())).collect()
+    }
+
+    #[test]
+    fn changes_are_made_in_order_after_dispatch() {
+        let mut root: Layout<Resources> = LayoutBuilder::new().with_widget(text("a")).build();
+        root.child_mut(0).unwrap().properties_mut().add_handler(Phase::Bubble, Box::new(|_, _, context| {
+            context.queue_change(|root: &mut Layout<Resources>| root.push(text("b"))).unwrap();
+            context.queue_change(|root: &mut Layout<Resources>| root.insert(0, text("c"))).unwrap();
+        }));
+
+        let mut app_state = ();
+        let mut context = EventContext::new(&[0], None, Modifiers::default(), &mut app_state);
+        dispatch(&mut root, &Event::MouseEnter, &mut context);
+        assert_eq!(ids(&root), vec!["a"]);
+
+        assert!(context.apply_changes(&mut root));
+        assert_eq!(ids(&root), vec!["c", "a", "b"]);
+        assert!(!context.apply_changes(&mut root));
+    }
+
+    #[test]
+    fn handlers_see_the_state_of_the_dispatch() {
+        let mut root: Layout<Resources> = LayoutBuilder::new().with_id("root").with_widget(text("a")).build();
+        root.properties_mut().add_handler(Phase::Capture, Box::new(|_, _, context| {
+            assert_eq!(context.phase(), Phase::Capture);
+            assert_eq!(context.id(), Some("root"));
+            *context.app_state::<Vec<&str>>().unwrap() = vec!["root"];
+            context.stop_propagation();
+        }));
+        root.child_mut(0).unwrap().properties_mut().add_handler(Phase::Bubble, Box::new(|_, _, context| {
+            context.app_state::<Vec<&str>>().unwrap().push("a");
+        }));
+
+        let mut reached = Vec::<&str>::new();
+        {
+            let mut context = EventContext::new(&[0], Some([1, 2]), Modifiers::default(), &mut reached);
+            dispatch(&mut root, &Event::MouseEnter, &mut context);
+            assert!(context.is_propagation_stopped());
+            assert_eq!(context.result(), EventResult::Ignored);
+        }
+        assert_eq!(reached, vec!["root"]);
+    }
+
+    #[test]
+    fn changes_for_other_resources_are_rejected() {
+        let mut app_state = ();
+        let mut context = EventContext::new(&[], None, Modifiers::default(), &mut app_state);
+        context.tree = Some(TypeId::of::<()>());
+
+        match context.queue_change(|root: &mut Layout<Resources>| root.push(text("a"))) {
+            Err(Error::TreeMismatch) => {},
+            result => panic!("expected a tree mismatch, got {:?}", result)
+        }
+        assert!(!context.apply_changes(&mut LayoutBuilder::<Resources>::new().build()));
+    }
+}
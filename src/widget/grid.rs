@@ -0,0 +1,259 @@
+// Copyright 2015 The Athena Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std;
+use std::any::Any;
+use gfx;
+use geometry::{self, Align, GridCell, Track};
+use markup;
+use widget;
+use render;
+use Error;
+
+/// Object that allows you to build grid widgets.
+pub struct GridBuilder<R: gfx::Resources> {
+    properties: widget::Properties,
+    grid: geometry::Grid,
+    widgets: Vec<(Box<widget::Widget<R>>, GridCell)>
+}
+
+impl<R: gfx::Resources> GridBuilder<R> {
+    /// Initializes a new `GridBuilder` with default values.
+    pub fn new() -> GridBuilder<R> {
+        GridBuilder {
+            properties: widget::Properties::new(),
+            grid: geometry::Grid::default(),
+            widgets: Vec::new()
+        }
+    }
+
+    builder_properties!();
+
+    /// Requests the sizes of the grid's columns, from left to right.
+    pub fn with_columns(mut self, columns: Vec<Track>) -> GridBuilder<R> {
+        self.grid.columns = columns;
+        self
+    }
+
+    /// Requests the sizes of the grid's rows, from top to bottom.
+    pub fn with_rows(mut self, rows: Vec<Track>) -> GridBuilder<R> {
+        self.grid.rows = rows;
+        self
+    }
+
+    /// Requests space to be left between columns and between rows.
+    pub fn with_spacing(mut self, spacing: [i32; 2]) -> GridBuilder<R> {
+        self.grid.spacing = spacing;
+        self
+    }
+
+    /// Requests a child widget to be placed in the given cells.
+    pub fn with_widget(mut self, widget: Box<widget::Widget<R>>, cell: GridCell) -> GridBuilder<R> {
+        self.widgets.push((widget, cell));
+        self
+    }
+
+    pub fn build(self) -> Grid<R> {
+        Grid {
+            properties: self.properties,
+            grid: self.grid,
+            widgets: self.widgets
+        }
+    }
+}
+
+/// Represents a widget placing other widgets in the cells of rows and columns.
+pub struct Grid<R: gfx::Resources> {
+    properties: widget::Properties,
+    grid: geometry::Grid,
+    widgets: Vec<(Box<widget::Widget<R>>, GridCell)>
+}
+
+impl<R: gfx::Resources> Grid<R> {
+    /// Gets the sizes of the grid's columns.
+    pub fn columns(&self) -> &[Track] { &self.grid.columns }
+    /// Sets the sizes of the grid's columns.
+    pub fn set_columns(&mut self, columns: Vec<Track>) {
+        self.grid.columns = columns;
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the sizes of the grid's rows.
+    pub fn rows(&self) -> &[Track] { &self.grid.rows }
+    /// Sets the sizes of the grid's rows.
+    pub fn set_rows(&mut self, rows: Vec<Track>) {
+        self.grid.rows = rows;
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the space left between columns and between rows.
+    pub fn spacing(&self) -> [i32; 2] { self.grid.spacing }
+    /// Sets the space left between columns and between rows.
+    pub fn set_spacing(&mut self, spacing: [i32; 2]) {
+        self.grid.spacing = spacing;
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the amount of child widgets.
+    pub fn len(&self) -> usize { self.widgets.len() }
+    /// Checks if the grid has no child widgets.
+    pub fn is_empty(&self) -> bool { self.widgets.is_empty() }
+
+    /// Adds a child widget placed in the given cells.
+    pub fn push(&mut self, widget: Box<widget::Widget<R>>, cell: GridCell) {
+        self.widgets.push((widget, cell));
+        self.properties.layout.invalidate();
+    }
+
+    /// Removes and returns the child widget at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<widget::Widget<R>> {
+        self.properties.layout.invalidate();
+        self.widgets.remove(index).0
+    }
+
+    /// Removes all child widgets.
+    pub fn clear(&mut self) {
+        self.widgets.clear();
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the cells the child widget at `index` is placed in.
+    pub fn cell(&self, index: usize) -> Option<&GridCell> {
+        self.widgets.get(index).map(|w| &w.1)
+    }
+
+    /// Moves the child widget at `index` to other cells.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_cell(&mut self, index: usize, cell: GridCell) {
+        self.widgets[index].1 = cell;
+        self.properties.layout.invalidate();
+    }
+
+    /// Describes this grid and all of its children as a markup element.
+    pub fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("grid");
+
+        let list = |tracks: &[Track]| tracks.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ");
+        if !self.grid.columns.is_empty() {
+            element.attributes.insert(String::from("columns"), list(&self.grid.columns));
+        }
+        if !self.grid.rows.is_empty() {
+            element.attributes.insert(String::from("rows"), list(&self.grid.rows));
+        }
+        if self.grid.spacing != [0, 0] {
+            element.attributes.insert(
+                String::from("spacing"), format!("{} {}", self.grid.spacing[0], self.grid.spacing[1]));
+        }
+
+        element.children = self.widgets.iter().map(|&(ref widget, ref cell)| {
+            let mut child = widget.to_element();
+            cell_to_attributes(cell, &mut child);
+            child
+        }).collect();
+        element
+    }
+
+    /// Writes this grid and all of its children as a markup document.
+    pub fn to_markup(&self) -> String {
+        self.to_element().to_markup()
+    }
+
+    /// Describes the measured child widgets to be placed in the grid, including their margin.
+    fn items(&self) -> Vec<geometry::GridItem> {
+        self.widgets.iter().map(|&(ref widget, cell)| geometry::GridItem {
+            size: widget::measured_outer_size(&**widget),
+            cell: cell
+        }).collect()
+    }
+}
+
+impl<R: gfx::Resources + 'static> widget::Widget<R> for Grid<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "grid" }
+
+    fn compute_size(&mut self, constraints: &geometry::Constraints) -> [i32; 2] {
+        let style = &self.properties.computed_style;
+        let insets = style.insets([0; 4]);
+        let insets_size = [insets[1] + insets[3], insets[0] + insets[2]];
+
+        // Children are measured at their own size, the tracks decide the rest
+        let mut available = geometry::Constraints::loose(constraints.shrink(insets_size).max);
+        if let Some(width) = style.width { available.max[0] = std::cmp::max(width - insets_size[0], 0); }
+        if let Some(height) = style.height { available.max[1] = std::cmp::max(height - insets_size[1], 0); }
+        for &mut (ref mut widget, _) in &mut self.widgets {
+            widget::measure_child(&mut **widget, &available);
+        }
+
+        let content = self.grid.measure(&self.items());
+        style.size_or([content[0] + insets_size[0], content[1] + insets_size[1]])
+    }
+
+    fn arrange_children(&mut self, area: &render::RenderArea) {
+        let content = self.properties.computed_style.content_area(area.position, area.size, [0; 4]);
+        let areas = self.grid.arrange(&content, &self.items());
+        for (&mut (ref mut widget, _), cell_area) in self.widgets.iter_mut().zip(areas.iter()) {
+            widget::arrange_child(&mut **widget, cell_area);
+        }
+    }
+
+    fn to_element(&self) -> markup::Element { Grid::to_element(self) }
+
+    fn child_count(&self) -> usize { self.widgets.len() }
+    fn child(&self, index: usize) -> Option<&widget::Widget<R>> { self.widgets.get(index).map(|w| &*w.0) }
+    fn child_mut(&mut self, index: usize) -> Option<&mut widget::Widget<R>> {
+        match self.widgets.get_mut(index) {
+            Some(w) => Some(&mut *w.0),
+            None => None
+        }
+    }
+
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        let area = self.properties.layout.area();
+        try!(renderer.render_box(area.position, area.size, &self.properties.computed_style, None, None));
+
+        for &(ref widget, _) in &self.widgets {
+            try!(widget.render(renderer));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the cells a child widget is placed in as attributes of its element.
+fn cell_to_attributes(cell: &GridCell, element: &mut markup::Element) {
+    let mut set = |name: &str, value: String| { element.attributes.insert(String::from(name), value); };
+
+    set("row", cell.row.to_string());
+    set("column", cell.column.to_string());
+    if cell.row_span > 1 {
+        set("row-span", cell.row_span.to_string());
+    }
+    if cell.column_span > 1 {
+        set("column-span", cell.column_span.to_string());
+    }
+    if cell.align[0] != Align::Stretch {
+        set("halign", String::from(cell.align[0].name()));
+    }
+    if cell.align[1] != Align::Stretch {
+        set("valign", String::from(cell.align[1].name()));
+    }
+}
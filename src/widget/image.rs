@@ -12,73 +12,129 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::any::Any;
 use std::default::Default;
 use gfx;
 use gfx_texture;
+use geometry;
+use markup;
 use widget;
 use render;
-use Event;
+use Error;
 
 /// Object that allows you to build image widgets.
 pub struct ImageBuilder {
+    properties: widget::Properties,
     image_source: String,
-    size: [i32; 2]
+    size: Option<[i32; 2]>
 }
 
 impl ImageBuilder {
     /// Initializes a new `ImageBuilder` with default values.
     pub fn new() -> ImageBuilder {
         ImageBuilder {
+            properties: widget::Properties::new(),
             image_source: String::default(),
-            size: [0, 0]
+            size: None
         }
     }
 
+    builder_properties!();
+
     /// Requests an image to be loaded in to be displayed.
     pub fn with_source(mut self, source: &str) -> ImageBuilder {
         self.image_source = String::from(source);
         self
     }
 
-    /// Requests a size to show the widget at.
+    /// Requests a size to show the widget at, instead of the image's own size.
     pub fn with_size(mut self, size: [i32; 2]) -> ImageBuilder {
-        self.size = size;
+        self.size = Some(size);
         self
     }
 
-    /// Builds the widget.
-    pub fn build_boxed<R: gfx::Resources, F: gfx::Factory<R>>(self, factory: &mut F) -> Box<Image<R>> {
-        let settings = gfx_texture::Settings::new();
-        let tex = gfx_texture::Texture::from_path(factory, self.image_source, &settings).unwrap();
+    /// Builds the widget, loading the requested image into a texture.
+    pub fn build_boxed<R: gfx::Resources, F: render::TextureLoader<R>>(self, factory: &mut F) -> Result<Box<Image<R>>, Error> {
+        let tex = try!(factory.load_texture(&self.image_source));
 
-        Box::new(Image {
+        Ok(Box::new(Image {
+            properties: self.properties,
+            source: self.image_source,
             texture: tex,
             size: self.size
-        })
+        }))
     }
 }
 
 /// Represents a widget with an image content.
 pub struct Image<R: gfx::Resources> {
+    properties: widget::Properties,
+    source: String,
     texture: gfx_texture::Texture<R>,
-    size: [i32;2]
+    /// The size requested for the widget, if it's not the image's own size.
+    size: Option<[i32; 2]>
+}
+
+impl<R: gfx::Resources> Image<R> {
+    /// Gets the source the current image was loaded from.
+    pub fn source(&self) -> &str { &self.source }
+
+    /// Loads a new image to be displayed, keeping the current one if loading fails.
+    pub fn set_source<F: render::TextureLoader<R>>(&mut self, factory: &mut F, source: &str) -> Result<(), Error> {
+        self.texture = try!(factory.load_texture(source));
+        self.source = String::from(source);
+        Ok(())
+    }
+
+    /// Gets the size the image is shown at, which is the image's own size
+    /// unless another size was requested.
+    pub fn size(&self) -> [i32; 2] {
+        match self.size {
+            Some(size) => size,
+            None => {
+                let handle = self.texture.handle();
+                let info = handle.get_info();
+                [info.width as i32, info.height as i32]
+            }
+        }
+    }
+    /// Sets the size the image is shown at.
+    pub fn set_size(&mut self, size: [i32; 2]) {
+        self.size = Some(size);
+        self.properties.layout.invalidate();
+    }
 }
 
-impl<R: gfx::Resources> widget::Widget<R> for Image<R> {
-    fn raise_event(&mut self, _: &Event, _: &render::RenderArea, offset: &mut render::RenderOffset) {
-        offset.position[1] += self.size[1];
+impl<R: gfx::Resources + 'static> widget::Widget<R> for Image<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "image" }
 
-        // We don't care about events
+    fn is_opaque(&self) -> bool { true }
+
+    fn compute_size(&mut self, _constraints: &geometry::Constraints) -> [i32; 2] {
+        self.properties.computed_style.size_or(self.size())
     }
 
-    fn render(
-        &self, renderer: &mut render::Renderer<R>,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset)
-    {
-        let pos = [prev_area.position[0] + offset.position[0], prev_area.position[1] + offset.position[1]];
-        renderer.render_rect_textured(pos, self.size, self.texture.handle());
+    fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("image");
+        element.attributes.insert(String::from("src"), self.source.clone());
+        if let Some(size) = self.size {
+            element.attributes.insert(String::from("width"), size[0].to_string());
+            element.attributes.insert(String::from("height"), size[1].to_string());
+        }
+        element
+    }
 
-        // Increment the rendering offset for the next widget
-        offset.position[1] += self.size[1];
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        // An image from the style replaces the texture, so it can change with the widget's state
+        let area = self.properties.layout.area();
+        renderer.render_box(
+            area.position, area.size, &self.properties.computed_style,
+            None, Some(self.texture.handle()))
     }
 }
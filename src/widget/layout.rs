@@ -12,19 +12,82 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std;
+use std::any::Any;
 use gfx;
+use geometry;
+use markup;
 use widget;
 use render;
-use Event;
+use style;
+use Error;
 
 pub enum LayoutBackground {
     None,
     Color([f32; 3])
 }
 
+/// The direction a layout places its child widgets in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From top to bottom.
+    Vertical,
+    /// From left to right.
+    Horizontal,
+    /// From bottom to top.
+    VerticalReverse,
+    /// From right to left.
+    HorizontalReverse
+}
+
+impl Direction {
+    /// Parses a direction from its name as used in markup, such as `horizontal-reverse`.
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "vertical" => Some(Direction::Vertical),
+            "horizontal" => Some(Direction::Horizontal),
+            "vertical-reverse" => Some(Direction::VerticalReverse),
+            "horizontal-reverse" => Some(Direction::HorizontalReverse),
+            _ => None
+        }
+    }
+
+    /// Gets the name of the direction as used in markup.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Direction::Vertical => "vertical",
+            Direction::Horizontal => "horizontal",
+            Direction::VerticalReverse => "vertical-reverse",
+            Direction::HorizontalReverse => "horizontal-reverse"
+        }
+    }
+
+    /// Gets the index of the axis widgets are placed along, 0 for horizontal and 1 for vertical.
+    pub fn axis(&self) -> usize {
+        match *self {
+            Direction::Horizontal | Direction::HorizontalReverse => 0,
+            Direction::Vertical | Direction::VerticalReverse => 1
+        }
+    }
+
+    /// Checks if widgets are placed starting at the end of the layout.
+    pub fn is_reversed(&self) -> bool {
+        *self == Direction::VerticalReverse || *self == Direction::HorizontalReverse
+    }
+}
+
+impl Default for Direction {
+    fn default() -> Direction { Direction::Vertical }
+}
+
 /// Object that allows you to build layout widgets.
 pub struct LayoutBuilder<R: gfx::Resources> {
+    properties: widget::Properties,
     background: LayoutBackground,
+    direction: Direction,
+    spacing: i32,
+    clipping: bool,
+    flex: Option<geometry::Flex>,
     widgets: Vec<Box<widget::Widget<R>>>
 }
 
@@ -32,17 +95,49 @@ impl<R: gfx::Resources> LayoutBuilder<R> {
     /// Initializes a new `LayoutBuilder` with default values.
     pub fn new() -> LayoutBuilder<R> {
         LayoutBuilder {
+            properties: widget::Properties::new(),
             background: LayoutBackground::None,
+            direction: Direction::Vertical,
+            spacing: 0,
+            clipping: false,
+            flex: None,
             widgets: Vec::new()
         }
     }
 
+    builder_properties!();
+
     pub fn with_background_color(mut self, color: [u8; 3]) -> LayoutBuilder<R> {
         let rgb = [(color[0] as f32)/255.0, (color[1] as f32)/255.0, (color[2] as f32)/255.0];
         self.background = LayoutBackground::Color(rgb);
         self
     }
 
+    /// Requests a direction to place the child widgets in.
+    pub fn with_direction(mut self, direction: Direction) -> LayoutBuilder<R> {
+        self.direction = direction;
+        self
+    }
+
+    /// Requests space to be left between the child widgets.
+    pub fn with_spacing(mut self, spacing: i32) -> LayoutBuilder<R> {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Requests child widgets to be cut off where they extend outside of the layout.
+    pub fn with_clipping(mut self, clipping: bool) -> LayoutBuilder<R> {
+        self.clipping = clipping;
+        self
+    }
+
+    /// Requests child widgets to be placed in flexible lines, growing and
+    /// shrinking to fill the layout as their styles allow.
+    pub fn with_flex(mut self, flex: geometry::Flex) -> LayoutBuilder<R> {
+        self.flex = Some(flex);
+        self
+    }
+
     pub fn with_widget(mut self, widget: Box<widget::Widget<R>>) -> LayoutBuilder<R> {
         self.widgets.push(widget);
         self
@@ -50,15 +145,26 @@ impl<R: gfx::Resources> LayoutBuilder<R> {
 
     pub fn build(self) -> Layout<R> {
         Layout {
+            properties: self.properties,
             background: self.background,
+            direction: self.direction,
+            spacing: self.spacing,
+            clipping: self.clipping,
+            flex: self.flex,
             widgets: self.widgets
         }
     }
 }
 
-/// Represents a widget with other widgets as content.
+/// Represents a widget with other widgets as content, which can be nested in
+/// other layouts.
 pub struct Layout<R: gfx::Resources> {
+    properties: widget::Properties,
     background: LayoutBackground,
+    direction: Direction,
+    spacing: i32,
+    clipping: bool,
+    flex: Option<geometry::Flex>,
     widgets: Vec<Box<widget::Widget<R>>>
 }
 
@@ -67,35 +173,417 @@ impl<R: gfx::Resources> Layout<R> {
         self.background = background;
     }
 
-    pub fn raise_event(&mut self, event: &Event, prev_area: &render::RenderArea) {
-        let mut offset = render::RenderOffset {position: [0, 0]};
-        for widget in &mut self.widgets {
-            widget.raise_event(event, prev_area, &mut offset);
+    /// Gets the direction the child widgets are placed in.
+    pub fn direction(&self) -> Direction { self.direction }
+    /// Sets the direction the child widgets are placed in.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        self.properties.layout.invalidate();
+    }
+
+    /// Gets the space left between the child widgets.
+    pub fn spacing(&self) -> i32 { self.spacing }
+    /// Sets the space left between the child widgets.
+    pub fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+        self.properties.layout.invalidate();
+    }
+
+    /// Checks if child widgets are cut off where they extend outside of the layout.
+    pub fn clipping(&self) -> bool { self.clipping }
+    /// Sets if child widgets are cut off where they extend outside of the layout.
+    pub fn set_clipping(&mut self, clipping: bool) { self.clipping = clipping; }
+
+    /// Gets the options for placing child widgets in flexible lines, if they're placed that way.
+    pub fn flex(&self) -> Option<&geometry::Flex> { self.flex.as_ref() }
+    /// Sets the options for placing child widgets in flexible lines, or places
+    /// them one after another again if `None`.
+    pub fn set_flex(&mut self, flex: Option<geometry::Flex>) {
+        self.flex = flex;
+        self.properties.layout.invalidate();
+    }
+
+    /// Describes this layout and all of its children as a markup element.
+    pub fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("layout");
+
+        if let LayoutBackground::Color(c) = self.background {
+            let to_u8 = |v: f32| (v * 255.0).round() as u8;
+            element.attributes.insert(
+                String::from("background"),
+                format!("#{:02x}{:02x}{:02x}", to_u8(c[0]), to_u8(c[1]), to_u8(c[2])));
+        }
+        if self.direction != Direction::Vertical {
+            element.attributes.insert(String::from("direction"), String::from(self.direction.name()));
+        }
+        if self.spacing != 0 {
+            element.attributes.insert(String::from("spacing"), self.spacing.to_string());
+        }
+        if self.clipping {
+            element.attributes.insert(String::from("clip"), String::from("true"));
+        }
+        if let Some(ref flex) = self.flex {
+            element.attributes.insert(String::from("flex"), String::from("true"));
+            if flex.justify != geometry::Justify::Start {
+                element.attributes.insert(String::from("justify"), String::from(flex.justify.name()));
+            }
+            if flex.align != geometry::Align::Stretch {
+                element.attributes.insert(String::from("align"), String::from(flex.align.name()));
+            }
+            if flex.wrap {
+                element.attributes.insert(String::from("wrap"), String::from("true"));
+            }
+            if flex.line_spacing != 0 {
+                element.attributes.insert(String::from("line-spacing"), flex.line_spacing.to_string());
+            }
         }
+
+        element.children = self.widgets.iter().map(|w| w.to_element()).collect();
+        element
     }
 
-    pub fn render(&self, renderer: &mut render::Renderer<R>, prev_area: &render::RenderArea)
-    {
-        self.render_background(renderer, prev_area);
+    /// Writes this layout and all of its children as a markup document, which
+    /// can be loaded again using `markup::load_layout`.
+    pub fn to_markup(&self) -> String {
+        self.to_element().to_markup()
+    }
+
+    /// Gets the amount of child widgets.
+    pub fn len(&self) -> usize { self.widgets.len() }
+    /// Checks if the layout has no child widgets.
+    pub fn is_empty(&self) -> bool { self.widgets.is_empty() }
+
+    /// Gets an iterator over the child widgets.
+    pub fn iter(&self) -> std::slice::Iter<Box<widget::Widget<R>>> { self.widgets.iter() }
+    /// Gets an iterator over the child widgets as mutable.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<Box<widget::Widget<R>>> { self.widgets.iter_mut() }
 
-        // Render all child widgets
-        let mut offset = render::RenderOffset {position: [0, 0]};
+    /// Adds a child widget after all existing ones.
+    pub fn push(&mut self, widget: Box<widget::Widget<R>>) {
+        self.widgets.push(widget);
+        self.properties.layout.invalidate();
+    }
+
+    /// Inserts a child widget at `index`, shifting all widgets after it.
+    ///
+    /// Panics if `index` is greater than the amount of child widgets.
+    pub fn insert(&mut self, index: usize, widget: Box<widget::Widget<R>>) {
+        self.widgets.insert(index, widget);
+        self.properties.layout.invalidate();
+    }
+
+    /// Removes and returns the child widget at `index`, shifting all widgets after it.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<widget::Widget<R>> {
+        self.properties.layout.invalidate();
+        self.widgets.remove(index)
+    }
+
+    /// Removes and returns the child widget with the given id, if there is one.
+    pub fn remove_by_id(&mut self, id: &str) -> Option<Box<widget::Widget<R>>> {
+        match self.widgets.iter().position(|w| w.id() == Some(id)) {
+            Some(index) => Some(self.remove(index)),
+            None => None
+        }
+    }
+
+    /// Removes all child widgets.
+    pub fn clear(&mut self) {
+        self.widgets.clear();
+        self.properties.layout.invalidate();
+    }
+
+    /// Finds a descendant widget by its id.
+    pub fn find_by_id(&self, id: &str) -> Option<&widget::Widget<R>> {
         for widget in &self.widgets {
-            widget.render(renderer, prev_area, &mut offset);
+            if widget.id() == Some(id) {
+                return Some(&**widget);
+            }
+            if let Some(found) = widget.find_by_id(id) {
+                return Some(found);
+            }
         }
+
+        None
     }
 
-    fn render_background(
-        &self,
-        renderer: &mut render::Renderer<R>,
-        area: &render::RenderArea)
-    {
+    /// Finds a descendant widget by its id as mutable.
+    pub fn find_by_id_mut(&mut self, id: &str) -> Option<&mut widget::Widget<R>> {
+        for widget in &mut self.widgets {
+            if widget.id() == Some(id) {
+                return Some(&mut **widget);
+            }
+            if let Some(found) = widget.find_by_id_mut(id) {
+                return Some(found);
+            }
+        }
+
+        None
+    }
+}
+
+impl<R: gfx::Resources + 'static> widget::Widget<R> for Layout<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "layout" }
+
+    fn compute_size(&mut self, constraints: &geometry::Constraints) -> [i32; 2] {
+        let axis = self.direction.axis();
+        let cross = 1 - axis;
+        let style = &self.properties.computed_style;
+        let insets = style.insets([0; 4]);
+        let insets_size = [insets[1] + insets[3], insets[0] + insets[2]];
+
+        // A size set by the style limits the content more than the constraints do
+        let mut available = constraints.shrink(insets_size);
+        if let Some(width) = style.width { available.max[0] = std::cmp::max(width - insets_size[0], 0); }
+        if let Some(height) = style.height { available.max[1] = std::cmp::max(height - insets_size[1], 0); }
+
+        // Fit all children one after another, with the widest one deciding the
+        // other dimension. Children placed after one another get the space
+        // left after the previous ones, in flexible lines they can shrink instead.
+        let mut content = [0, 0];
+        for (i, widget) in self.widgets.iter_mut().enumerate() {
+            let size = widget::measure_child(&mut **widget, &available);
+            let spacing = if i == 0 { 0 } else { self.spacing };
+            content[axis] += size[axis] + spacing;
+            content[cross] = std::cmp::max(content[cross], size[cross]);
+
+            if self.flex.is_none() {
+                let mut used = [0, 0];
+                used[axis] = size[axis] + self.spacing;
+                available = available.shrink(used);
+            }
+        }
+
+        style.size_or([content[0] + insets_size[0], content[1] + insets_size[1]])
+    }
+
+    fn arrange_children(&mut self, area: &render::RenderArea) {
+        let content = self.properties.computed_style.content_area(area.position, area.size, [0; 4]);
+        let areas = match self.flex {
+            Some(ref flex) => {
+                let items: Vec<geometry::FlexItem> = self.widgets.iter().map(|w| flex_item(&**w)).collect();
+                flex.arrange(&content, self.direction, self.spacing, &items)
+            },
+            None => {
+                let sizes: Vec<[i32; 2]> = self.widgets.iter().map(|w| widget::measured_outer_size(&**w)).collect();
+                geometry::stack(&content, self.direction, self.spacing, &sizes)
+            }
+        };
+
+        for (widget, child_area) in self.widgets.iter_mut().zip(areas.iter()) {
+            widget::arrange_child(&mut **widget, child_area);
+        }
+    }
+
+    fn to_element(&self) -> markup::Element { Layout::to_element(self) }
+
+    fn child_count(&self) -> usize { self.widgets.len() }
+    fn child(&self, index: usize) -> Option<&widget::Widget<R>> { self.widgets.get(index).map(|w| &**w) }
+    fn child_mut(&mut self, index: usize) -> Option<&mut widget::Widget<R>> {
+        match self.widgets.get_mut(index) {
+            Some(w) => Some(&mut **w),
+            None => None
+        }
+    }
+
+    fn find_by_id(&self, id: &str) -> Option<&widget::Widget<R>> { Layout::find_by_id(self, id) }
+    fn find_by_id_mut(&mut self, id: &str) -> Option<&mut widget::Widget<R>> { Layout::find_by_id_mut(self, id) }
+
+    fn is_opaque(&self) -> bool {
+        match self.background {
+            LayoutBackground::None => self.properties.styled_background,
+            LayoutBackground::Color(_) => true
+        }
+    }
+
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        // The layout's own background is set on it directly like an inline
+        // style, so it takes precedence over the background rules give it
+        let area = self.properties.layout.area();
         match self.background {
-            // Different background types render differently
-            LayoutBackground::None => {},
+            LayoutBackground::None =>
+                try!(renderer.render_box(area.position, area.size, &self.properties.computed_style, None, None)),
             LayoutBackground::Color(c) => {
-                renderer.render_rect_flat(area.position, area.size, c);
+                let style = style::Style { background: Some(c), .. self.properties.computed_style.clone() };
+                try!(renderer.render_box(area.position, area.size, &style, None, None));
             }
         }
+
+        if self.clipping {
+            renderer.push_clip(&area);
+        }
+
+        // Render all child widgets, making sure the clip is removed again on failure
+        let mut result = Ok(());
+        for widget in &self.widgets {
+            result = widget.render(renderer);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        if self.clipping {
+            renderer.pop_clip();
+        }
+
+        result
+    }
+}
+
+/// Describes a child widget to be placed in flexible lines, including its margin.
+fn flex_item<R: gfx::Resources>(widget: &widget::Widget<R>) -> geometry::FlexItem {
+    let style = &widget.properties().computed_style;
+    let margin = style.margin.unwrap_or([0; 4]);
+    let margins = [margin[1] + margin[3], margin[0] + margin[2]];
+
+    geometry::FlexItem {
+        size: widget::measured_outer_size(widget),
+        min_size: [
+            style.min_width.unwrap_or(0) + margins[0],
+            style.min_height.unwrap_or(0) + margins[1]],
+        max_size: [
+            style.max_width.map(|v| v + margins[0]).unwrap_or(std::i32::MAX),
+            style.max_height.map(|v| v + margins[1]).unwrap_or(std::i32::MAX)],
+        grow: style.flex_grow.unwrap_or(0.0),
+        shrink: style.flex_shrink.unwrap_or(1.0),
+        align: style.align_self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx;
+    use gfx_device_gl::Resources;
+    use geometry::Constraints;
+    use render::{RenderArea, Renderer};
+    use style::{Stylesheet, Theme};
+    use widget::{Text, TextBuilder, Widget};
+    use Error;
+    use super::*;
+
+    /// Records the colors of the rectangles rendered with it.
+    struct ColorRecorder {
+        colors: Vec<[f32; 3]>
+    }
+
+    impl Renderer<Resources> for ColorRecorder {
+        fn render_rect_flat(&mut self, _: [i32; 2], _: [i32; 2], color: [f32; 3]) -> Result<(), Error> {
+            self.colors.push(color);
+            Ok(())
+        }
+        fn render_rect_rounded(&mut self, _: [i32; 2], _: [i32; 2], _: i32, color: [f32; 3]) -> Result<(), Error> {
+            self.colors.push(color);
+            Ok(())
+        }
+        fn render_rect_textured(&mut self, _: [i32; 2], _: [i32; 2], _: gfx::handle::Texture<Resources>) -> Result<(), Error> { Ok(()) }
+        fn render_image(&mut self, _: [i32; 2], _: [i32; 2], _: &str) -> Result<(), Error> { Ok(()) }
+        fn render_text(&mut self, _: [i32; 2], _: &str, _: [f32; 3], _: u8) -> Result<(), Error> { Ok(()) }
+        fn push_clip(&mut self, _: &RenderArea) {}
+        fn pop_clip(&mut self) {}
+    }
+
+    /// Records the areas of the rectangles rendered with it.
+    struct AreaRecorder {
+        areas: Vec<RenderArea>
+    }
+
+    impl Renderer<Resources> for AreaRecorder {
+        fn render_rect_flat(&mut self, position: [i32; 2], size: [i32; 2], _: [f32; 3]) -> Result<(), Error> {
+            self.areas.push(RenderArea { position: position, size: size });
+            Ok(())
+        }
+        fn render_rect_rounded(&mut self, position: [i32; 2], size: [i32; 2], _: i32, _: [f32; 3]) -> Result<(), Error> {
+            self.areas.push(RenderArea { position: position, size: size });
+            Ok(())
+        }
+        fn render_rect_textured(&mut self, _: [i32; 2], _: [i32; 2], _: gfx::handle::Texture<Resources>) -> Result<(), Error> { Ok(()) }
+        fn render_image(&mut self, _: [i32; 2], _: [i32; 2], _: &str) -> Result<(), Error> { Ok(()) }
+        fn render_text(&mut self, _: [i32; 2], _: &str, _: [f32; 3], _: u8) -> Result<(), Error> { Ok(()) }
+        fn push_clip(&mut self, _: &RenderArea) {}
+        fn pop_clip(&mut self) {}
+    }
+
+    fn render_background(layout: &mut Layout<Resources>, stylesheet: &Stylesheet) -> Option<[f32; 3]> {
+        stylesheet.apply(layout, None);
+        layout.measure(&Constraints::tight([100, 100]));
+        layout.arrange(RenderArea { position: [0, 0], size: [100, 100] });
+
+        let mut renderer = ColorRecorder { colors: Vec::new() };
+        layout.render(&mut renderer).unwrap();
+        renderer.colors.first().cloned()
+    }
+
+    #[test]
+    fn background_color_overrides_the_theme() {
+        let theme = Stylesheet::new().with_base(&Theme::dark().to_stylesheet());
+        let mut layout = LayoutBuilder::<Resources>::new().build();
+        assert_eq!(render_background(&mut layout, &theme), Some(Theme::dark().palette.background));
+
+        let mut layout = LayoutBuilder::<Resources>::new().with_background_color([255, 0, 0]).build();
+        assert_eq!(render_background(&mut layout, &theme), Some([1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn background_color_overrides_stylesheet_rules() {
+        let stylesheet = Stylesheet::parse("layout { background: #00ff00; }").unwrap();
+        let mut layout = LayoutBuilder::<Resources>::new().with_background_color([255, 0, 0]).build();
+        assert_eq!(render_background(&mut layout, &stylesheet), Some([1.0, 0.0, 0.0]));
+
+        layout.set_background(LayoutBackground::None);
+        assert_eq!(render_background(&mut layout, &stylesheet), Some([0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn over_padded_layouts_render_empty_children() {
+        let stylesheet = Stylesheet::parse(concat!(
+            "#outer { padding: 40; width: 20; height: 20; background: #ff0000; }\n",
+            "#inner { background: #00ff00; }")).unwrap();
+        let mut layout = LayoutBuilder::<Resources>::new()
+            .with_id("outer")
+            .with_widget(Box::new(LayoutBuilder::new().with_id("inner").build()))
+            .build();
+        stylesheet.apply(&mut layout, None);
+        layout.measure(&Constraints::loose([100, 100]));
+        layout.arrange(RenderArea { position: [0, 0], size: [20, 20] });
+
+        let mut renderer = AreaRecorder { areas: Vec::new() };
+        layout.render(&mut renderer).unwrap();
+        assert_eq!(renderer.areas.len(), 2);
+        assert_eq!(renderer.areas[0].size, [20, 20]);
+        assert!(renderer.areas[1].size[0] >= 0 && renderer.areas[1].size[1] >= 0);
+    }
+
+    #[test]
+    fn invalidating_a_descendant_marks_only_its_ancestors() {
+        let mut layout = LayoutBuilder::<Resources>::new()
+            .with_widget(Box::new(LayoutBuilder::new()
+                .with_id("inner")
+                .with_widget(TextBuilder::new().with_id("deep").with_text("a").build_boxed())
+                .build()))
+            .with_widget(TextBuilder::new().with_id("sibling").with_text("b").build_boxed())
+            .build();
+        let area = RenderArea { position: [0, 0], size: [100, 100] };
+        layout.measure(&Constraints::tight(area.size));
+        layout.arrange(area);
+        assert!(!layout.needs_measure() && !layout.needs_arrange());
+
+        layout.find_by_id_mut("deep").unwrap().as_any_mut().downcast_mut::<Text<Resources>>().unwrap()
+            .set_text("a longer text");
+        assert!(layout.needs_measure() && layout.needs_arrange());
+        assert!(layout.find_by_id("inner").unwrap().needs_measure());
+        assert!(!layout.find_by_id("sibling").unwrap().needs_measure());
+
+        layout.measure(&Constraints::tight(area.size));
+        layout.arrange(area);
+        assert!(!layout.needs_measure() && !layout.needs_arrange());
+        assert!(!layout.find_by_id("deep").unwrap().needs_measure());
+        assert!(layout.find_by_id("deep").unwrap().area().size[0] > 0);
     }
 }
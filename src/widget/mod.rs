@@ -14,29 +14,543 @@
 
 //! Contains widgets and builders needed to build them.
 
+use std;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use gfx;
+use geometry;
+use markup;
 use render;
+use style;
+use Error;
 use Event;
 
+/// Implements the builder methods for the properties every widget has, and
+/// access to them, inside of the `impl` block of a widget's builder.
+macro_rules! builder_properties {
+    () => {
+        /// Gets the properties the widget is built with.
+        pub fn properties_mut(&mut self) -> &mut ::widget::Properties {
+            &mut self.properties
+        }
+
+        /// Requests an id for the widget, used to look it up later.
+        pub fn with_id(mut self, id: &str) -> Self {
+            self.properties.id = Some(String::from(id));
+            self
+        }
+
+        /// Requests a class to be added to the widget.
+        pub fn with_class(mut self, class: &str) -> Self {
+            self.properties.classes.push(String::from(class));
+            self
+        }
+
+        /// Requests a style to be set directly on the widget, overriding any stylesheet.
+        pub fn with_style(mut self, style: ::style::Style) -> Self {
+            self.properties.style = style;
+            self
+        }
+
+        /// Requests a style that overrides the regular style while the widget is in a state.
+        pub fn with_state_style(mut self, state: ::widget::Interaction, style: ::style::Style) -> Self {
+            self.properties.state_styles.push((state, style));
+            self
+        }
+
+        /// Requests the widget to start out disabled.
+        pub fn with_disabled(mut self, disabled: bool) -> Self {
+            self.properties.state.disabled = disabled;
+            self
+        }
+
+        /// Requests the widget to start out checked.
+        pub fn with_checked(mut self, checked: bool) -> Self {
+            self.properties.state.checked = checked;
+            self
+        }
+
+        /// Requests a function to be called when events reach the widget in a
+        /// phase, or when the widget is their target.
+        pub fn with_handler(mut self, phase: ::widget::Phase, handler: ::widget::EventHandler) -> Self {
+            self.properties.add_handler(phase, handler);
+            self
+        }
+    }
+}
+
+mod anchor;
 mod button;
+mod dispatch;
+mod grid;
 mod image;
 mod layout;
 mod text;
 
+pub use widget::anchor::*;
 pub use widget::button::*;
+pub use widget::dispatch::*;
+pub use widget::grid::*;
 pub use widget::image::*;
 pub use widget::layout::*;
 pub use widget::text::*;
 
+/// Properties shared by every kind of widget.
+#[derive(Default)]
+pub struct Properties {
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+
+    /// The style set directly on the widget, overriding any stylesheet.
+    pub style: style::Style,
+    /// Styles set directly on the widget that only apply while it's in a state.
+    pub state_styles: Vec<(Interaction, style::Style)>,
+    /// The style resulting from applying a stylesheet, read by the widget when rendering.
+    pub computed_style: style::Style,
+    /// If the computed style's background was given to the widget by the
+    /// application, directly or through a stylesheet, not only by a theme.
+    pub styled_background: bool,
+
+    /// The current interaction state of the widget.
+    pub state: State,
+
+    /// The geometry computed for the widget by the last layout.
+    pub layout: LayoutCache,
+
+    /// The functions called when events reach the widget, with the phase they react in.
+    pub handlers: Vec<(Phase, EventHandler)>
+}
+
+impl Properties {
+    /// Initializes a new `Properties` without an id, classes or style.
+    pub fn new() -> Properties {
+        Properties::default()
+    }
+
+    /// Adds a function called when events reach the widget in a phase. The
+    /// widget also calls it when it's the target, in any phase.
+    pub fn add_handler(&mut self, phase: Phase, handler: EventHandler) {
+        self.handlers.push((phase, handler));
+    }
+
+    /// Creates a markup element with these properties.
+    pub fn to_element(&self, tag: &str) -> markup::Element {
+        let mut element = markup::Element::new(tag);
+        element.id = self.id.clone();
+        element.classes = self.classes.clone();
+
+        if self.state.disabled {
+            element.attributes.insert(String::from("disabled"), String::from("true"));
+        }
+        if self.state.checked {
+            element.attributes.insert(String::from("checked"), String::from("true"));
+        }
+
+        element
+    }
+}
+
+impl std::fmt::Debug for Properties {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Properties")
+            .field("id", &self.id)
+            .field("classes", &self.classes)
+            .field("style", &self.style)
+            .field("state_styles", &self.state_styles)
+            .field("computed_style", &self.computed_style)
+            .field("styled_background", &self.styled_background)
+            .field("state", &self.state)
+            .field("layout", &self.layout)
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+/// The size a widget was measured at and the area it was arranged in, which
+/// are reused until the widget is invalidated.
+#[derive(Debug, Default)]
+pub struct LayoutCache {
+    /// The constraints the widget was last measured within, and the size of its box.
+    measured: Option<(geometry::Constraints, [i32; 2])>,
+    /// The area the widget's box was last arranged in.
+    area: Option<render::RenderArea>,
+    /// The width of the widget's text as measured by a `render::TextMeasurer`,
+    /// for widgets that have text.
+    text_width: Option<i32>,
+    /// Marks the widget as containing invalidated descendants, shared with
+    /// its children so they can mark it.
+    stale: Rc<StaleFlag>
+}
+
+/// Marks a widget whose descendants were invalidated since it was last laid out.
+#[derive(Debug, Default)]
+struct StaleFlag {
+    measure: Cell<bool>,
+    arrange: Cell<bool>,
+    /// The flag of the widget's parent, as of when the parent was last measured.
+    parent: RefCell<Option<Rc<StaleFlag>>>
+}
+
+impl LayoutCache {
+    /// Gets the size of the widget's box as last measured.
+    pub fn measured_size(&self) -> [i32; 2] {
+        self.measured.map(|m| m.1).unwrap_or([0, 0])
+    }
+
+    /// Gets the area of the widget's box as last arranged.
+    pub fn area(&self) -> render::RenderArea {
+        self.area.unwrap_or(render::RenderArea { position: [0, 0], size: [0, 0] })
+    }
+
+    /// Gets the width of the widget's text as last measured, if it was.
+    pub fn text_width(&self) -> Option<i32> { self.text_width }
+
+    /// Discards the measured size, area and text width, so they're computed
+    /// again by the next layout.
+    pub fn invalidate(&mut self) {
+        self.measured = None;
+        self.area = None;
+        self.text_width = None;
+
+        // Ancestors reuse their geometry unless they're told this changed
+        let mut parent = self.stale.parent.borrow().clone();
+        while let Some(flag) = parent {
+            flag.measure.set(true);
+            flag.arrange.set(true);
+            parent = flag.parent.borrow().clone();
+        }
+    }
+}
+
+/// A state a widget can be in as a result of user interaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interaction {
+    Hovered,
+    Pressed,
+    Focused,
+    Disabled,
+    Checked
+}
+
+impl Interaction {
+    /// Gets all states, in the order their styles are applied in.
+    pub fn all() -> [Interaction; 5] {
+        [
+            Interaction::Hovered,
+            Interaction::Focused,
+            Interaction::Checked,
+            Interaction::Pressed,
+            Interaction::Disabled
+        ]
+    }
+}
+
+/// The interaction states a widget is currently in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    /// The mouse is over the widget.
+    pub hovered: bool,
+    /// A mouse button was pressed on the widget and hasn't been released yet.
+    pub pressed: bool,
+    /// The widget receives keyboard input, set by the `Gui` as focus moves.
+    pub focused: bool,
+    /// The widget can't be interacted with.
+    pub disabled: bool,
+    /// The widget is toggled on, set by the application.
+    pub checked: bool
+}
+
+impl State {
+    /// Checks if the widget is in a state.
+    pub fn is(&self, interaction: Interaction) -> bool {
+        match interaction {
+            Interaction::Hovered => self.hovered,
+            Interaction::Pressed => self.pressed,
+            Interaction::Focused => self.focused,
+            Interaction::Disabled => self.disabled,
+            Interaction::Checked => self.checked
+        }
+    }
+}
+
 /// An interface for referencing to any kind of widget.
 pub trait Widget<R: gfx::Resources> {
-    /// Handles an event that may or may not apply to this widget.
-    fn raise_event(
-        &mut self, event: &Event,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset);
-
-    /// Writes rendering data for this widget to `data`.
-    fn render(
-        &self, renderer: &mut render::Renderer<R>,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset);
+    /// Gets the properties shared by all widgets as immutable.
+    fn properties(&self) -> &Properties;
+    /// Gets the properties shared by all widgets as mutable.
+    fn properties_mut(&mut self) -> &mut Properties;
+
+    /// Gets this widget as `Any`, allowing it to be downcast to its concrete type.
+    fn as_any(&self) -> &Any;
+    /// Gets this widget as mutable `Any`, allowing it to be downcast to its concrete type.
+    fn as_any_mut(&mut self) -> &mut Any;
+
+    /// Gets the element name of this widget, used in markup and stylesheets.
+    fn tag(&self) -> &'static str;
+
+    /// Describes this widget and its content as a markup element.
+    fn to_element(&self) -> markup::Element;
+
+    /// Computes the size of this widget's box within constraints, measuring
+    /// its children. The box includes the padding and border but not the margin.
+    ///
+    /// This is called by `measure` when the cached size is out of date.
+    fn compute_size(&mut self, constraints: &geometry::Constraints) -> [i32; 2];
+
+    /// Arranges the children of this widget within its box, using the sizes
+    /// they were measured at.
+    ///
+    /// This is called by `arrange` when the cached area is out of date.
+    fn arrange_children(&mut self, _area: &render::RenderArea) {}
+
+    /// Measures the text of this widget and its descendants that changed
+    /// since it was last measured. This is done before they're measured, so
+    /// widgets with text can size themselves to it.
+    fn measure_text(&mut self, measurer: &mut render::TextMeasurer) -> Result<(), Error> {
+        for i in 0..self.child_count() {
+            if let Some(child) = self.child_mut(i) {
+                try!(child.measure_text(measurer));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the size of this widget's box within constraints, reusing the
+    /// last size if nothing changed since.
+    fn measure(&mut self, constraints: &geometry::Constraints) -> [i32; 2] {
+        if let Some((measured_with, size)) = self.properties().layout.measured {
+            if measured_with == *constraints && !self.needs_measure() {
+                return size;
+            }
+        }
+
+        // Children invalidated from now on have to tell this widget
+        let stale = self.properties().layout.stale.clone();
+        for i in 0..self.child_count() {
+            if let Some(child) = self.child_mut(i) {
+                *child.properties().layout.stale.parent.borrow_mut() = Some(stale.clone());
+            }
+        }
+
+        let size = self.compute_size(constraints);
+        self.properties_mut().layout.measured = Some((*constraints, size));
+        stale.measure.set(false);
+        size
+    }
+
+    /// Places this widget's box in an area and arranges its children in it,
+    /// unless it was already arranged there and nothing changed since.
+    fn arrange(&mut self, area: render::RenderArea) {
+        if self.properties().layout.area == Some(area) && !self.needs_arrange() {
+            return;
+        }
+
+        self.properties_mut().layout.area = Some(area);
+        self.arrange_children(&area);
+        self.properties().layout.stale.arrange.set(false);
+    }
+
+    /// Gets the area of this widget's box as last arranged.
+    fn area(&self) -> render::RenderArea { self.properties().layout.area() }
+
+    /// Discards this widget's cached geometry, so it's measured and arranged
+    /// again by the next layout. Widgets invalidate themselves when changes
+    /// such as new text affect their size.
+    fn invalidate(&mut self) { self.properties_mut().layout.invalidate(); }
+
+    /// Checks if this widget or any of its descendants has to be measured again.
+    fn needs_measure(&self) -> bool {
+        let layout = &self.properties().layout;
+        layout.measured.is_none() || layout.stale.measure.get()
+    }
+
+    /// Checks if this widget or any of its descendants has to be arranged again.
+    fn needs_arrange(&self) -> bool {
+        let layout = &self.properties().layout;
+        layout.area.is_none() || layout.stale.arrange.get()
+    }
+
+    /// Gets the amount of direct children of this widget.
+    fn child_count(&self) -> usize { 0 }
+    /// Gets a direct child of this widget by its index.
+    fn child(&self, _index: usize) -> Option<&Widget<R>> { None }
+    /// Gets a direct child of this widget by its index as mutable.
+    fn child_mut(&mut self, _index: usize) -> Option<&mut Widget<R>> { None }
+
+    /// Checks if this widget can receive keyboard input, which it's given
+    /// when it's clicked or reached with Tab.
+    fn is_focusable(&self) -> bool { false }
+
+    /// Finds the deepest widget at a point, returning the path of child
+    /// indices leading to it from this widget. The path is empty if no child
+    /// is at the point, and `None` is returned if this widget isn't either.
+    fn widget_at(&self, point: [i32; 2]) -> Option<Vec<usize>> {
+        if !self.area().contains(point) {
+            return None;
+        }
+
+        // Later children are rendered over earlier ones, so they're found first
+        for i in (0..self.child_count()).rev() {
+            if let Some(mut path) = self.child(i).and_then(|c| c.widget_at(point)) {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+
+        Some(Vec::new())
+    }
+
+    /// Finds a descendant of this widget by its id.
+    fn find_by_id(&self, id: &str) -> Option<&Widget<R>> {
+        for i in 0..self.child_count() {
+            if let Some(child) = self.child(i) {
+                if child.id() == Some(id) {
+                    return Some(child);
+                }
+                if let Some(found) = child.find_by_id(id) {
+                    return Some(found);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Finds a descendant of this widget by its id as mutable.
+    fn find_by_id_mut(&mut self, id: &str) -> Option<&mut Widget<R>> {
+        // Find out which child leads to the widget before borrowing it as mutable
+        let index = (0..self.child_count()).position(|i| match self.child(i) {
+            Some(child) => child.id() == Some(id) || child.find_by_id(id).is_some(),
+            None => false
+        });
+
+        let child = match index {
+            Some(i) => self.child_mut(i),
+            None => None
+        };
+
+        match child {
+            Some(child) => if child.id() == Some(id) { Some(child) } else { child.find_by_id_mut(id) },
+            None => None
+        }
+    }
+
+    /// Gets the id of this widget, if it has one.
+    fn id(&self) -> Option<&str> {
+        self.properties().id.as_ref().map(|v| &v[..])
+    }
+
+    /// Gets the current interaction state of this widget.
+    fn state(&self) -> &State { &self.properties().state }
+    /// Gets the current interaction state of this widget as mutable, used to
+    /// set the states applications control such as disabled and checked.
+    fn state_mut(&mut self) -> &mut State { &mut self.properties_mut().state }
+
+    /// Checks if this widget has been given a class.
+    fn has_class(&self, class: &str) -> bool {
+        self.properties().classes.iter().any(|c| c == class)
+    }
+
+    /// Checks if this widget keeps the mouse from reaching what's behind it,
+    /// such as when it's given a background. Backgrounds that only come from
+    /// a theme don't count, so the theme doesn't cover the whole window.
+    fn is_opaque(&self) -> bool { self.properties().styled_background }
+
+    /// Handles an event dispatched to this widget or through it, before the
+    /// handlers added to it are called. The context tells the phase the event
+    /// is in and lets the widget stop or consume it.
+    fn handle_event(&mut self, _event: &Event, _context: &mut EventContext) {}
+
+    /// Renders this widget in the area it was last arranged in.
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error>;
+}
+
+/// Finds a descendant of a widget by the path of child indices leading to it,
+/// such as the path `Widget::widget_at` returns.
+pub fn descendant<'a, R: gfx::Resources>(widget: &'a Widget<R>, path: &[usize]) -> Option<&'a Widget<R>> {
+    if path.is_empty() {
+        Some(widget)
+    } else {
+        widget.child(path[0]).and_then(|c| descendant(c, &path[1..]))
+    }
+}
+
+/// Finds a descendant of a widget by the path of child indices leading to it as mutable.
+pub fn descendant_mut<'a, R: gfx::Resources>(widget: &'a mut Widget<R>, path: &[usize]) -> Option<&'a mut Widget<R>> {
+    if path.is_empty() {
+        Some(widget)
+    } else {
+        widget.child_mut(path[0]).and_then(|c| descendant_mut(c, &path[1..]))
+    }
+}
+
+/// Gets the paths to a widget and all of its descendants that match a
+/// predicate, in the order they're in the tree.
+pub fn find_paths<R: gfx::Resources, P: Fn(&Widget<R>) -> bool>(widget: &Widget<R>, predicate: &P) -> Vec<Vec<usize>> {
+    let mut paths = Vec::new();
+    collect_paths(widget, predicate, &mut Vec::new(), &mut paths);
+    paths
+}
+
+fn collect_paths<R: gfx::Resources, P: Fn(&Widget<R>) -> bool>(
+    widget: &Widget<R>, predicate: &P, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>)
+{
+    if predicate(widget) {
+        paths.push(path.clone());
+    }
+
+    for i in 0..widget.child_count() {
+        if let Some(child) = widget.child(i) {
+            path.push(i);
+            collect_paths(child, predicate, path, paths);
+            path.pop();
+        }
+    }
+}
+
+/// Updates the states of a widget and all of its descendants, telling
+/// `update` for each of them if it's on a path of child indices from the widget.
+pub fn update_states<R: gfx::Resources, U: Fn(&mut State, bool)>(widget: &mut Widget<R>, path: Option<&[usize]>, update: &U) {
+    update(widget.state_mut(), path.is_some());
+
+    for i in 0..widget.child_count() {
+        let child_path = match path {
+            Some(path) if !path.is_empty() && path[0] == i => Some(&path[1..]),
+            _ => None
+        };
+
+        if let Some(child) = widget.child_mut(i) {
+            update_states(child, child_path, update);
+        }
+    }
+}
+
+/// Gets the size of a box together with the margin a style gives it.
+fn outer_size(style: &style::Style, size: [i32; 2]) -> [i32; 2] {
+    let margin = style.margin.unwrap_or([0; 4]);
+    [margin[3] + size[0] + margin[1], margin[0] + size[1] + margin[2]]
+}
+
+/// Measures a child widget within constraints, returning the size of its box
+/// together with its margin.
+fn measure_child<R: gfx::Resources>(widget: &mut Widget<R>, constraints: &geometry::Constraints) -> [i32; 2] {
+    let margin = widget.properties().computed_style.margin.unwrap_or([0; 4]);
+    let size = widget.measure(&constraints.shrink([margin[1] + margin[3], margin[0] + margin[2]]));
+    outer_size(&widget.properties().computed_style, size)
+}
+
+/// Gets the size of a child widget's box as last measured, together with its margin.
+fn measured_outer_size<R: gfx::Resources>(widget: &Widget<R>) -> [i32; 2] {
+    outer_size(&widget.properties().computed_style, widget.properties().layout.measured_size())
+}
+
+/// Arranges a child widget in an area that includes its margin.
+fn arrange_child<R: gfx::Resources>(widget: &mut Widget<R>, area: &render::RenderArea) {
+    let margin = widget.properties().computed_style.margin.unwrap_or([0; 4]);
+    widget.arrange(render::RenderArea {
+        position: [area.position[0] + margin[3], area.position[1] + margin[0]],
+        size: [area.size[0] - margin[1] - margin[3], area.size[1] - margin[0] - margin[2]]
+    });
 }
@@ -13,14 +13,18 @@
 // limitations under the License.
 
 use std;
+use std::any::Any;
 use std::default::Default;
 use gfx;
+use geometry;
+use markup;
 use widget;
 use render;
-use Event;
+use Error;
 
 /// Object that allows you to build text widgets.
 pub struct TextBuilder {
+    properties: widget::Properties,
     text: String
 }
 
@@ -28,10 +32,13 @@ impl TextBuilder {
     /// Initializes a new `TextBuilder` with default values.
     pub fn new() -> TextBuilder {
         TextBuilder {
+            properties: widget::Properties::new(),
             text: String::default()
         }
     }
 
+    builder_properties!();
+
     /// Requests a specific text content for the widget.
     pub fn with_text(mut self, text: &str) -> TextBuilder {
         self.text = String::from(text);
@@ -41,6 +48,7 @@ impl TextBuilder {
     /// Builds the widget.
     pub fn build_boxed<R: gfx::Resources>(self) -> Box<Text<R>> {
         Box::new(Text {
+            properties: self.properties,
             text: self.text,
             _r: std::marker::PhantomData
         })
@@ -49,33 +57,166 @@ impl TextBuilder {
 
 /// Represents a widget with a text content.
 pub struct Text<R: gfx::Resources> {
+    properties: widget::Properties,
     text: String,
 
     _r: std::marker::PhantomData<R>
 }
 
-impl<R: gfx::Resources> widget::Widget<R> for Text<R> {
-    fn raise_event(&mut self, _: &Event, _: &render::RenderArea, offset: &mut render::RenderOffset) {
-        let size = [(self.text.len()*18) as i32, 18];
-        offset.position[1] += size[1];
+impl<R: gfx::Resources> Text<R> {
+    /// Gets the text content of the widget.
+    pub fn text(&self) -> &str { &self.text }
+    /// Sets the text content of the widget.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = String::from(text);
+        self.properties.layout.invalidate();
+    }
+
+    fn font_size(&self) -> u8 {
+        self.properties.computed_style.font_size.unwrap_or(render::DEFAULT_FONT_SIZE)
+    }
+
+    fn size(&self) -> [i32; 2] {
+        let style = &self.properties.computed_style;
+        let insets = style.insets([0; 4]);
+
+        // The widest line decides the width, and every line adds to the height
+        let font_size = self.font_size();
+        let lines = std::cmp::max(self.text.lines().count(), 1);
+        let width = match self.properties.layout.text_width() {
+            Some(width) => width,
+            None => self.text.lines().map(|l| estimate_line_width(l, font_size)).max().unwrap_or(0)
+        };
+        let measured = [
+            width + insets[1] + insets[3],
+            lines as i32 * line_height(font_size) + insets[0] + insets[2]];
+
+        style.size_or(measured)
+    }
+}
+
+/// The average width of a character in the built-in font, relative to the font size.
+const AVERAGE_ADVANCE: f32 = 0.55;
+
+/// Estimates the width of a line of text at a font size, for text laid out
+/// without being measured first.
+fn estimate_line_width(line: &str, font_size: u8) -> i32 {
+    (line.chars().count() as f32 * font_size as f32 * AVERAGE_ADVANCE).ceil() as i32
+}
+
+/// Gets the height of a line of text at a font size.
+pub fn line_height(font_size: u8) -> i32 {
+    font_size as i32 + 5
+}
+
+impl<R: gfx::Resources + 'static> widget::Widget<R> for Text<R> {
+    fn properties(&self) -> &widget::Properties { &self.properties }
+    fn properties_mut(&mut self) -> &mut widget::Properties { &mut self.properties }
+
+    fn as_any(&self) -> &Any { self }
+    fn as_any_mut(&mut self) -> &mut Any { self }
+
+    fn tag(&self) -> &'static str { "text" }
+
+    fn measure_text(&mut self, measurer: &mut render::TextMeasurer) -> Result<(), Error> {
+        if self.properties.layout.text_width().is_some() {
+            return Ok(());
+        }
+
+        let font_size = self.font_size();
+        let mut width = 0;
+        for line in self.text.lines() {
+            width = std::cmp::max(width, try!(measurer.text_width(line, font_size)));
+        }
+
+        // The measured width changes the size, so measure the widget again
+        self.properties.layout.invalidate();
+        self.properties.layout.text_width = Some(width);
+        Ok(())
+    }
+
+    fn compute_size(&mut self, _constraints: &geometry::Constraints) -> [i32; 2] { self.size() }
+
+    fn to_element(&self) -> markup::Element {
+        let mut element = self.properties.to_element("text");
+        element.text = Some(self.text.clone());
+        element
+    }
+
+    fn render(&self, renderer: &mut render::Renderer<R>) -> Result<(), Error> {
+        let style = &self.properties.computed_style;
+        let area = self.properties.layout.area();
+        let (pos, size) = (area.position, area.size);
+
+        try!(renderer.render_box(pos, size, style, None, None));
+
+        // Render the actual text, a line at a time
+        let content = style.content_area(pos, size, [0; 4]);
+        let color = style.color.unwrap_or(render::DEFAULT_TEXT_COLOR);
+        let font_size = self.font_size();
+        for (i, line) in self.text.lines().enumerate() {
+            let line_pos = [content.position[0], content.position[1] + i as i32 * line_height(font_size)];
+            try!(renderer.render_text(line_pos, line, color, font_size));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx_device_gl::Resources;
+    use geometry::Constraints;
+    use render::{self, TextMeasurer};
+    use widget::Widget;
+    use Error;
+    use super::*;
+    use super::estimate_line_width;
 
-        // We don't care about events
+    /// Measures every character as 7 wide, counting the lines it measured.
+    struct FixedMeasurer {
+        lines: usize
     }
 
-    fn render(
-        &self, renderer: &mut render::Renderer<R>,
-        prev_area: &render::RenderArea, offset: &mut render::RenderOffset)
-    {
-        // TODO: Actually get width based on the width of the result
-        let pos = [
-            (prev_area.position[0] + offset.position[0]),
-            (prev_area.position[1] + offset.position[1])];
-        let size = [(self.text.len()*18) as i32, 18];
-
-        // Render the actual text
-        renderer.render_text(pos, &self.text);
-
-        // Increment the rendering offset for the next widget
-        offset.position[1] += size[1];
+    impl TextMeasurer for FixedMeasurer {
+        fn text_width(&mut self, text: &str, _size: u8) -> Result<i32, Error> {
+            self.lines += 1;
+            Ok(text.chars().count() as i32 * 7)
+        }
+    }
+
+    fn measure(text: &mut Text<Resources>, measurer: &mut FixedMeasurer) -> [i32; 2] {
+        text.measure_text(measurer).unwrap();
+        text.measure(&Constraints::unbounded())
+    }
+
+    #[test]
+    fn text_is_sized_to_its_widest_measured_line() {
+        let mut text = TextBuilder::new().with_text("ab\nabcd\nabc").build_boxed::<Resources>();
+        let mut measurer = FixedMeasurer { lines: 0 };
+        assert_eq!(measure(&mut text, &mut measurer), [28, 3*line_height(render::DEFAULT_FONT_SIZE)]);
+        assert_eq!(text.properties().layout.text_width(), Some(28));
+    }
+
+    #[test]
+    fn text_is_measured_again_once_it_changes() {
+        let mut text = TextBuilder::new().with_text("abc").build_boxed::<Resources>();
+        let mut measurer = FixedMeasurer { lines: 0 };
+        assert_eq!(measure(&mut text, &mut measurer)[0], 21);
+        assert_eq!(measure(&mut text, &mut measurer)[0], 21);
+        assert_eq!(measurer.lines, 1);
+
+        text.set_text("abcde");
+        assert_eq!(measure(&mut text, &mut measurer)[0], 35);
+        assert_eq!(measurer.lines, 2);
+    }
+
+    #[test]
+    fn measured_width_replaces_an_earlier_estimate() {
+        let mut text = TextBuilder::new().with_text("abc").build_boxed::<Resources>();
+        let estimate = text.measure(&Constraints::unbounded());
+        assert_eq!(estimate[0], estimate_line_width("abc", render::DEFAULT_FONT_SIZE));
+
+        assert_eq!(measure(&mut text, &mut FixedMeasurer { lines: 0 })[0], 21);
     }
 }